@@ -1,23 +1,93 @@
 use burn::backend::libtorch::LibTorchDevice;
-use price_data::DataConfig;
+use price_data::{DataConfig, TradingCalendar};
 mod ml_model;
 mod price_data;
 
+struct Args {
+    config: String,
+    command: Vec<String>,
+}
+
+// `cargo run -- [--config <path>] [command]`, the config path can also be set with `ML_STOCK_CONFIG`.
+fn parse_args() -> Args {
+    let mut config = std::env::var("ML_STOCK_CONFIG").unwrap_or_else(|_| "config.json".to_string());
+    let mut command = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => config = args.next().expect("--config requires a file path"),
+            _ => command.push(arg),
+        }
+    }
+
+    Args { config, command }
+}
+
 fn main() {
-    let data_config = DataConfig::new()
-        .expect("Error reading from configuration file")
-        .init();
+    let args = parse_args();
+
+    let data_config = DataConfig::new(&args.config).expect("Error reading from configuration file");
+
+    // `cargo run -- build-cache <exchange>` parses every csv of an exchange ahead of time.
+    if args.command.first().map(|arg| arg.as_str()) == Some("build-cache") {
+        let exchange = args.command.get(1).expect("usage: build-cache <exchange>");
+        let report = price_data::build_exchange_cache(exchange, data_config.schema_for(exchange))
+            .expect("Error reading exchange");
+        println!("{report}");
+        return;
+    }
 
-    let model = ml_model::StockPredictor::new("tmp/stock_predictor".to_string());
+    // `cargo run -- walk-forward` retrains the model through the validation range instead of training it once.
+    let walk_forward = args.command.first().map(|arg| arg.as_str()) == Some("walk-forward");
+
+    let mut data_config = data_config.clone();
+    if walk_forward {
+        // every fold trains on the days before it, so the training universe is loaded through the validation range.
+        data_config.train_end = data_config.train_end.max(data_config.validate_end);
+    }
+    let data_config = data_config.init().expect("Error loading price data");
+
+    let model = ml_model::StockPredictor::new(
+        data_config.artifact_dir.clone(),
+        data_config.experiment.clone(),
+        data_config.calendar,
+    );
 
     let device = LibTorchDevice::default();
 
+    let calendar = TradingCalendar::new(data_config.calendar, &data_config.validate_companies);
+
+    // `cargo run -- cv` cross-validates the experiment and its variants on the training universe.
+    if args.command.first().map(|arg| arg.as_str()) == Some("cv") {
+        model.cross_validate(data_config.train_companies, device);
+        return;
+    }
+
+    if walk_forward {
+        model.walk_forward(
+            &data_config.train_companies,
+            &data_config.validate_companies,
+            data_config.validate_start,
+            data_config.validate_end,
+            &calendar,
+            device,
+        );
+        return;
+    }
+
     model.train_model(data_config.train_companies, device.clone());
 
     model.validate_model(
         data_config.validate_companies,
         data_config.validate_start,
         data_config.validate_end,
+        &calendar,
         device.clone(),
     );
+
+    if let Some(test) = data_config.test {
+        let calendar = TradingCalendar::new(data_config.calendar, &test.companies);
+        model.validate_model(test.companies, test.start, test.end, &calendar, device);
+    }
 }
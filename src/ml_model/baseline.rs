@@ -0,0 +1,299 @@
+use std::fmt;
+
+use burn::tensor::{backend::Backend, Data, Tensor};
+use serde::{Deserialize, Serialize};
+
+use super::{data::PriceDataBatch, model::Predictor};
+
+/// A trivial predictor the trained model is compared against, configured by name, such as `"last_value"` or `"ar_3"`.
+/// Baselines only read the raw closes of the window.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Baseline {
+    /// The last close of the window.
+    LastValue,
+    /// Extends the line from the first to the last close of the window.
+    Drift,
+    /// The mean of the last closes, the whole window when no period is given.
+    MovingAverage(Option<usize>),
+    /// Extends the least squares line through the closes of the window.
+    LinearRegression,
+    /// An autoregressive model of order p, fit by least squares on every window and forecast step by step.
+    Autoregressive(usize),
+}
+
+impl TryFrom<String> for Baseline {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        let period = |prefix: &str| {
+            name.strip_prefix(prefix)
+                .and_then(|period| period.parse::<usize>().ok())
+                .filter(|period| *period > 0)
+        };
+
+        let baseline = match name.as_str() {
+            "last_value" => Baseline::LastValue,
+            "drift" => Baseline::Drift,
+            "moving_average" => Baseline::MovingAverage(None),
+            "linear_regression" => Baseline::LinearRegression,
+            _ => match (period("moving_average_"), period("ar_")) {
+                (Some(period), _) => Baseline::MovingAverage(Some(period)),
+                (_, Some(order)) => Baseline::Autoregressive(order),
+                _ => return Err(format!("unknown baseline \"{name}\"")),
+            },
+        };
+        Ok(baseline)
+    }
+}
+
+impl From<Baseline> for String {
+    fn from(baseline: Baseline) -> Self {
+        baseline.to_string()
+    }
+}
+
+impl fmt::Display for Baseline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Baseline::LastValue => write!(f, "last_value"),
+            Baseline::Drift => write!(f, "drift"),
+            Baseline::MovingAverage(None) => write!(f, "moving_average"),
+            Baseline::MovingAverage(Some(period)) => write!(f, "moving_average_{period}"),
+            Baseline::LinearRegression => write!(f, "linear_regression"),
+            Baseline::Autoregressive(order) => write!(f, "ar_{order}"),
+        }
+    }
+}
+
+impl Baseline {
+    /// Predicts the close `horizon` days after the last close of the window.
+    pub fn forecast(&self, closes: &[f64], horizon: usize) -> f64 {
+        let n = closes.len();
+        let last = closes[n - 1];
+
+        match *self {
+            Baseline::LastValue => last,
+            Baseline::Drift if n > 1 => last + horizon as f64 * (last - closes[0]) / (n - 1) as f64,
+            Baseline::Drift => last,
+            Baseline::MovingAverage(period) => {
+                let period = period.unwrap_or(n).min(n);
+                closes[n - period..].iter().sum::<f64>() / period as f64
+            }
+            Baseline::LinearRegression => {
+                let xs: Vec<f64> = (0..n).map(|x| x as f64).collect();
+                match least_squares(&xs, closes) {
+                    Some((intercept, slope)) => intercept + slope * (n - 1 + horizon) as f64,
+                    None => last,
+                }
+            }
+            Baseline::Autoregressive(order) => autoregressive(closes, order, horizon),
+        }
+    }
+}
+
+// Fits y = intercept + slope * x.
+fn least_squares(xs: &[f64], ys: &[f64]) -> Option<(f64, f64)> {
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+    let cov: f64 = xs
+        .iter()
+        .zip(ys)
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let var: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    if var == 0.0 {
+        return None;
+    }
+    let slope = cov / var;
+    Some((mean_y - slope * mean_x, slope))
+}
+
+// Windows that are too short or too flat to fit the model fall back to the last close.
+fn autoregressive(closes: &[f64], order: usize, horizon: usize) -> f64 {
+    let n = closes.len();
+    let last = closes[n - 1];
+    // an intercept and `order` coefficients need more equations than unknowns.
+    if n <= 2 * order + 1 {
+        return last;
+    }
+
+    // normal equations of y_t = c + a_1 * y_(t-1) + ... + a_p * y_(t-p).
+    let size = order + 1;
+    let mut xtx = vec![vec![0.0; size]; size];
+    let mut xty = vec![0.0; size];
+    for t in order..n {
+        let row: Vec<f64> = std::iter::once(1.0)
+            .chain((1..=order).map(|lag| closes[t - lag]))
+            .collect();
+        for i in 0..size {
+            xty[i] += row[i] * closes[t];
+            for j in 0..size {
+                xtx[i][j] += row[i] * row[j];
+            }
+        }
+    }
+
+    let coefficients = match solve(xtx, xty) {
+        Some(coefficients) => coefficients,
+        None => return last,
+    };
+
+    let mut history = closes.to_vec();
+    for _ in 0..horizon {
+        let len = history.len();
+        let next = coefficients[0]
+            + (1..=order)
+                .map(|lag| coefficients[lag] * history[len - lag])
+                .sum::<f64>();
+        history.push(next);
+    }
+
+    match history.last() {
+        Some(prediction) if prediction.is_finite() => *prediction,
+        _ => last,
+    }
+}
+
+// Gaussian elimination with partial pivoting.
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let size = b.len();
+    for col in 0..size {
+        let pivot = (col..size).max_by(|i, j| a[*i][col].abs().total_cmp(&a[*j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+
+        let pivot_row = a[col].clone();
+        for row in col + 1..size {
+            let factor = a[row][col] / pivot_row[col];
+            for (value, pivot) in a[row].iter_mut().zip(pivot_row.iter()).skip(col) {
+                *value -= factor * pivot;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = vec![0.0; size];
+    for row in (0..size).rev() {
+        let sum: f64 = (row + 1..size).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    Some(x)
+}
+
+// Baselines forecast the close and encode it the same way as the target of the batch.
+impl<B: Backend> Predictor<B> for Baseline {
+    fn predict(&self, batch: &PriceDataBatch<B>) -> Tensor<B, 2> {
+        let predictions: Vec<f64> = batch
+            .closes
+            .iter()
+            .zip(batch.contexts.iter())
+            .map(|(closes, context)| {
+                let future = self.forecast(closes, context.horizon);
+                batch.target_mode.encode_prediction(future, context)
+            })
+            .collect();
+
+        let predictions = Data::new(predictions.clone(), [predictions.len(), 1].into());
+        Tensor::from_data(predictions.convert(), &batch.data.device())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn baseline(name: &str) -> Baseline {
+        Baseline::try_from(name.to_string()).unwrap()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-6, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn parses_and_prints_names() {
+        for name in [
+            "last_value",
+            "drift",
+            "moving_average",
+            "moving_average_5",
+            "linear_regression",
+            "ar_3",
+        ] {
+            assert_eq!(baseline(name).to_string(), name);
+        }
+        for name in ["ar_0", "moving_average_x", "naive"] {
+            assert!(Baseline::try_from(name.to_string()).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn naive_forecasts() {
+        let closes = [1.0, 2.0, 4.0, 5.0];
+
+        assert_eq!(baseline("last_value").forecast(&closes, 3), 5.0);
+        // the line from 1 to 5 rises by 4 / 3 a day.
+        assert_close(baseline("drift").forecast(&closes, 3), 9.0);
+        assert_eq!(baseline("moving_average").forecast(&closes, 3), 3.0);
+        assert_eq!(baseline("moving_average_2").forecast(&closes, 3), 4.5);
+        assert_eq!(baseline("moving_average_10").forecast(&closes, 3), 3.0);
+    }
+
+    #[test]
+    fn linear_regression_extends_a_line() {
+        let closes: Vec<f64> = (0..10).map(|x| 3.0 + 0.5 * x as f64).collect();
+
+        assert_close(baseline("linear_regression").forecast(&closes, 1), 8.0);
+        assert_close(baseline("linear_regression").forecast(&closes, 4), 9.5);
+        assert_close(baseline("linear_regression").forecast(&[7.0; 5], 2), 7.0);
+    }
+
+    #[test]
+    fn autoregressive_recovers_a_known_process() {
+        // y_t = 1 + 0.5 * y_(t-1) - 0.2 * y_(t-2)
+        let step = |y1: f64, y2: f64| 1.0 + 0.5 * y1 - 0.2 * y2;
+        let mut closes = vec![10.0, 4.0];
+        for t in 2..30 {
+            closes.push(step(closes[t - 1], closes[t - 2]));
+        }
+        let mut expected = closes.clone();
+        for _ in 0..3 {
+            let n = expected.len();
+            expected.push(step(expected[n - 1], expected[n - 2]));
+        }
+
+        assert_close(baseline("ar_2").forecast(&closes, 3), expected[32]);
+    }
+
+    #[test]
+    fn autoregressive_falls_back_to_the_last_close() {
+        // too few closes for the number of coefficients.
+        assert_eq!(baseline("ar_3").forecast(&[1.0, 2.0, 3.0, 4.0], 1), 4.0);
+        // a flat window has no unique fit.
+        assert_eq!(baseline("ar_2").forecast(&[5.0; 20], 1), 5.0);
+    }
+
+    #[test]
+    fn solves_linear_systems() {
+        // the first pivot is zero, so the rows have to be swapped.
+        let a = vec![
+            vec![0.0, 2.0, 1.0],
+            vec![1.0, 1.0, 1.0],
+            vec![2.0, 1.0, 3.0],
+        ];
+        let x = solve(a, vec![7.0, 6.0, 13.0]).unwrap();
+
+        for (actual, expected) in x.iter().zip([1.0, 2.0, 3.0]) {
+            assert_close(*actual, expected);
+        }
+        assert_eq!(
+            solve(vec![vec![1.0, 2.0], vec![2.0, 4.0]], vec![1.0, 2.0]),
+            None
+        );
+    }
+}
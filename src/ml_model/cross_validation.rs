@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::ExperimentConfig;
+
+/// Settings of the `cv` command, a purged k-fold cross-validation of the training universe.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrossValidationConfig {
+    pub folds: usize,
+    // Experiments to compare, every variant lists the experiment keys it changes, such as `{"architecture": "gru"}`.
+    pub variants: Vec<Value>,
+}
+
+impl Default for CrossValidationConfig {
+    fn default() -> Self {
+        CrossValidationConfig {
+            folds: 5,
+            variants: Vec::new(),
+        }
+    }
+}
+
+impl CrossValidationConfig {
+    /// The experiments to cross-validate, `base` followed by every variant named by its changes to `base`.
+    pub fn experiments(
+        &self,
+        base: &ExperimentConfig,
+    ) -> Result<Vec<(String, ExperimentConfig)>, String> {
+        let base_value = serde_json::to_value(base).map_err(|err| err.to_string())?;
+        let mut experiments = vec![("experiment".to_string(), base.clone())];
+        for variant in self.variants.iter() {
+            let changes = variant.as_object().ok_or(format!(
+                "experiment.cross_validation.variants must be objects, found {variant}"
+            ))?;
+
+            let mut value = base_value.clone();
+            for (key, change) in changes {
+                // indexing would add a misspelled key, which the experiment then silently ignores.
+                if base_value.get(key).is_none() {
+                    return Err(format!(
+                        "invalid variant {variant}: unknown experiment key \"{key}\""
+                    ));
+                }
+                value[key] = change.clone();
+            }
+
+            let mut experiment: ExperimentConfig = serde_json::from_value(value)
+                .map_err(|err| format!("invalid variant {variant}: {err}"))?;
+            // the variants are not cross-validated themselves.
+            experiment.cross_validation.variants = Vec::new();
+            experiment
+                .validate()
+                .map_err(|err| format!("invalid variant {variant}: {err}"))?;
+
+            experiments.push((variant.to_string(), experiment));
+        }
+        Ok(experiments)
+    }
+}
+
+/// Prints the validation loss of every fold and their mean and standard deviation for every experiment, skipped
+/// folds are NaN and left out of the mean.
+pub fn print_results(results: &[(String, Vec<f64>)]) {
+    let folds = results
+        .iter()
+        .map(|(_, losses)| losses.len())
+        .max()
+        .unwrap_or(0);
+
+    print!("{:<40}{:>12}{:>12}", "", "mean", "std");
+    for fold in 0..folds {
+        print!("{:>12}", format!("fold {fold}"));
+    }
+    println!();
+
+    for (name, losses) in results {
+        let finite: Vec<f64> = losses
+            .iter()
+            .cloned()
+            .filter(|loss| loss.is_finite())
+            .collect();
+        let mean = finite.iter().sum::<f64>() / finite.len().max(1) as f64;
+        let variance = finite.iter().map(|loss| (loss - mean).powi(2)).sum::<f64>()
+            / finite.len().max(1) as f64;
+
+        print!("{name:<40}{mean:>12.6}{:>12.6}", variance.sqrt());
+        for loss in losses {
+            print!("{loss:>12.6}");
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ml_model::Architecture;
+
+    fn config(variants: &str) -> CrossValidationConfig {
+        CrossValidationConfig {
+            folds: 5,
+            variants: serde_json::from_str(variants).unwrap(),
+        }
+    }
+
+    #[test]
+    fn variants_change_the_base_experiment() {
+        let base = ExperimentConfig::default();
+        let experiments = config(r#"[{"architecture": "gru", "window_size": 20}]"#)
+            .experiments(&base)
+            .unwrap();
+
+        assert_eq!(experiments.len(), 2);
+        assert_eq!(experiments[0].0, "experiment");
+        let (name, variant) = &experiments[1];
+        assert_eq!(name, r#"{"architecture":"gru","window_size":20}"#);
+        assert_eq!(variant.architecture, Architecture::Gru);
+        assert_eq!(variant.window_size, 20);
+        assert_eq!(variant.hidden_size, base.hidden_size);
+    }
+
+    #[test]
+    fn unknown_variant_keys_are_rejected() {
+        let err = config(r#"[{"windw_size": 20}]"#)
+            .experiments(&ExperimentConfig::default())
+            .unwrap_err();
+        assert!(
+            err.contains("unknown experiment key \"windw_size\""),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn invalid_variants_are_rejected() {
+        let base = ExperimentConfig::default();
+        assert!(config(r#"[["gru"]]"#).experiments(&base).is_err());
+        assert!(config(r#"[{"window_size": "20"}]"#)
+            .experiments(&base)
+            .is_err());
+        assert!(config(r#"[{"dropout": 1.5}]"#).experiments(&base).is_err());
+    }
+}
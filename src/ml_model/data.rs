@@ -3,32 +3,46 @@ use burn::{
     tensor::{backend::Backend, Data, Tensor},
 };
 
-use super::data_loader::NormBurnPriceDataItem;
+use super::{
+    data_loader::NormBurnPriceDataItem,
+    target::{TargetContext, TargetMode},
+};
 
 #[derive(Clone)]
 pub struct PriceDataBatcher<B: Backend> {
     device: B::Device,
+    target_mode: TargetMode,
 }
 
 impl<B: Backend> PriceDataBatcher<B> {
-    pub fn new(device: B::Device) -> Self {
-        Self { device }
+    pub fn new(device: B::Device, target_mode: TargetMode) -> Self {
+        Self {
+            device,
+            target_mode,
+        }
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct PriceDataBatch<B: Backend> {
-    pub data: Tensor<B, 2>,
+    // [batch, seq_len, n_features]
+    pub data: Tensor<B, 3>,
     pub targets: Tensor<B, 1>,
+    // The raw closes and target context of every window, in the order of the batch.
+    pub closes: Vec<Vec<f64>>,
+    pub contexts: Vec<TargetContext>,
+    pub target_mode: TargetMode,
 }
 
 impl<B: Backend> Batcher<NormBurnPriceDataItem, PriceDataBatch<B>> for PriceDataBatcher<B> {
     fn batch(&self, items: Vec<NormBurnPriceDataItem>) -> PriceDataBatch<B> {
         let data = items
             .iter()
-            .map(|row| Data::<f64, 1>::from(row.data))
-            .map(|data| Tensor::<B, 1>::from_data(data.convert(), &self.device))
-            .map(|data| data.reshape([1, 32]))
+            .map(|row| {
+                Data::<f64, 2>::new(row.data.clone(), [row.seq_len(), row.n_features].into())
+            })
+            .map(|data| Tensor::<B, 2>::from_data(data.convert(), &self.device))
+            .map(|data| data.unsqueeze::<3>())
             .collect();
 
         let targets = items
@@ -40,6 +54,12 @@ impl<B: Backend> Batcher<NormBurnPriceDataItem, PriceDataBatch<B>> for PriceData
         let data = Tensor::cat(data, 0);
         let targets = Tensor::cat(targets, 0);
 
-        return PriceDataBatch { data, targets };
+        return PriceDataBatch {
+            data,
+            targets,
+            closes: items.iter().map(|row| row.closes.clone()).collect(),
+            contexts: items.iter().map(|row| row.context).collect(),
+            target_mode: self.target_mode,
+        };
     }
 }
@@ -1,166 +1,1044 @@
-use burn::data::dataset::{Dataset, InMemDataset};
+use burn::data::dataset::Dataset;
+use chrono::{Days, NaiveDate};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 
-use crate::price_data::CompaniesPriceData;
+use std::{
+    ops::Range,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
 
-use super::CHUNK_SIZE;
+use crate::price_data::{CalendarKind, CompaniesPriceData, PriceDataItem, TradingCalendar};
 
-#[derive(Copy, Deserialize, Serialize, Debug, Clone)]
+use super::{
+    experiment::ExperimentConfig,
+    features::{feature_rows, Feature},
+    normalization::{Normalization, Normalizer},
+    target::{TargetContext, TargetMode},
+};
+
+// `data` holds the window row by row, `n_features` values for every day.
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct BurnPriceDataItem {
-    pub data: [f64; 32],
+    pub data: Vec<f64>,
+    pub n_features: usize,
+    pub closes: Vec<f64>,
+    // The close the target is encoded from.
     pub target: f64,
 }
 
-#[derive(Copy, Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct NormBurnPriceDataItem {
-    pub data: [f64; 32],
+    pub data: Vec<f64>,
+    pub n_features: usize,
+    // The raw closes of the window, for predictors that forecast prices.
+    pub closes: Vec<f64>,
     pub target: f64,
-    pub min: f64,
-    pub max: f64,
+    pub context: TargetContext,
 }
 
 impl NormBurnPriceDataItem {
     pub fn from_data_vec(
         data: Vec<f64>,
+        n_features: usize,
+        closes: Vec<f64>,
         target: f64,
-        min: f64,
-        max: f64,
+        context: TargetContext,
     ) -> Result<NormBurnPriceDataItem, ()> {
-        if data.len() != 32 {
+        if data.is_empty() || n_features == 0 || data.len() != closes.len() * n_features {
             return Err(());
         }
-        let mut data_array = [0.0; 32];
-        for i in 0..32 {
-            data_array[i] = data[i];
-        }
         Ok(NormBurnPriceDataItem {
-            data: data_array,
+            data,
+            n_features,
+            closes,
             target,
-            min,
-            max,
+            context,
         })
     }
+
+    pub fn seq_len(&self) -> usize {
+        self.data.len() / self.n_features
+    }
 }
 
 impl BurnPriceDataItem {
-    pub fn from_data_vec(data: Vec<f64>, target: f64) -> Result<BurnPriceDataItem, String> {
-        if data.len() != 32 {
+    pub fn from_data_vec(
+        data: Vec<f64>,
+        n_features: usize,
+        closes: Vec<f64>,
+        target: f64,
+    ) -> Result<BurnPriceDataItem, String> {
+        if closes.is_empty() {
+            return Err("Expected a window of at least one day, instead found none".to_string());
+        }
+        if data.len() != closes.len() * n_features {
             return Err(format!(
-                "Expected data length 32, instead found {}",
+                "Expected {} feature values for a window of {} days, instead found {}",
+                closes.len() * n_features,
+                closes.len(),
                 data.len()
             ));
         }
-        let mut data_array = [0.0; 32];
-        for i in 0..32 {
-            data_array[i] = data[i];
-        }
         Ok(BurnPriceDataItem {
-            data: data_array,
+            data,
+            n_features,
+            closes,
             target,
         })
     }
 
-    pub fn normalize(&self) -> Option<NormBurnPriceDataItem> {
-        let min = self.data.iter().cloned().fold(f64::INFINITY, f64::min);
-        let max = self.data.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    /// Builds the item for the feature rows in `window`, `rows` holds `n_features` values for every row of
+    /// `price_data`.
+    pub fn from_window(
+        rows: &[f64],
+        n_features: usize,
+        price_data: &[PriceDataItem],
+        window: Range<usize>,
+        target: f64,
+    ) -> Result<BurnPriceDataItem, String> {
+        let data = rows[window.start * n_features..window.end * n_features].to_vec();
+        let closes = price_data[window].iter().map(|item| item.close).collect();
+        BurnPriceDataItem::from_data_vec(data, n_features, closes, target)
+    }
 
-        if max - min == 0.0 {
+    /// Encodes the target, `horizon` days after the last day of the window. Returns None when the window has
+    /// missing values or the target cannot be encoded.
+    pub fn encode_target(
+        &self,
+        target_mode: TargetMode,
+        horizon: usize,
+    ) -> Option<(f64, TargetContext)> {
+        // windows that contain a day marked as missing by the calendar alignment cannot be used.
+        if self
+            .data
+            .iter()
+            .chain(self.closes.iter())
+            .any(|x| !x.is_finite())
+        {
             return None;
         }
 
-        let new_data = self.data.iter().map(|x| (x - min) / (max - min)).collect();
-        let target = (self.target - min) / (max - min);
+        let context = TargetContext::new(&self.closes, horizon);
+        let target = target_mode.encode(self.target, &context);
+        if !target.is_finite() {
+            return None;
+        }
+
+        Some((target, context))
+    }
+
+    /// Scales the features with the normalizer and encodes the target, `horizon` days after the last day of
+    /// the window. Returns None when the window has missing values or the target cannot be encoded.
+    pub fn normalize(
+        &self,
+        normalizer: &Normalizer,
+        target_mode: TargetMode,
+        horizon: usize,
+    ) -> Option<NormBurnPriceDataItem> {
+        let (target, context) = self.encode_target(target_mode, horizon)?;
+
+        let new_data = normalizer.normalize(&self.data, &self.closes);
+        if new_data.iter().any(|x| !x.is_finite()) {
+            return None;
+        }
 
-        return Some(NormBurnPriceDataItem::from_data_vec(new_data, target, min, max).unwrap());
+        return Some(
+            NormBurnPriceDataItem::from_data_vec(
+                new_data,
+                self.n_features,
+                self.closes.clone(),
+                target,
+                context,
+            )
+            .unwrap(),
+        );
     }
 }
 
 impl Dataset<NormBurnPriceDataItem> for TrainPriceDataSet {
     fn get(&self, idx: usize) -> Option<NormBurnPriceDataItem> {
-        self.dataset.get(idx)
+        let (company, offset) = *self.index.get(idx)?;
+        self.series[company].window(
+            offset,
+            self.window_size,
+            self.prediction_interval,
+            &self.normalizer,
+            self.target_mode,
+        )
     }
 
     fn len(&self) -> usize {
-        self.dataset.len()
+        self.index.len()
+    }
+}
+
+// The feature rows and the price data of a company, the windows are built from them when they are read.
+struct CompanySeries {
+    // The feature rows one after the other, `n_features` values for every row of the price data.
+    rows: Vec<f64>,
+    n_features: usize,
+    price_data: Vec<PriceDataItem>,
+    // The number of rows up to every row with a trading day of the calendar missing before it.
+    breaks: Vec<usize>,
+}
+
+impl CompanySeries {
+    fn new(
+        rows: Vec<f64>,
+        n_features: usize,
+        price_data: Vec<PriceDataItem>,
+        calendar: &TradingCalendar,
+    ) -> Self {
+        let mut breaks = Vec::with_capacity(price_data.len());
+        let mut count = 0;
+        for (idx, item) in price_data.iter().enumerate() {
+            // rows on days the calendar has no trading on are not gaps.
+            let gap = idx > 0
+                && calendar
+                    .next_trading_day(price_data[idx - 1].date)
+                    .is_some_and(|next| next < item.date);
+            if gap {
+                count += 1;
+            }
+            breaks.push(count);
+        }
+
+        CompanySeries {
+            rows,
+            n_features,
+            price_data,
+            breaks,
+        }
+    }
+
+    // Whether no trading day is missing from the rows `offset` to `offset + span`, a window that spans a gap in
+    // the data would silently join days that are not next to each other.
+    fn is_contiguous(&self, offset: usize, span: usize) -> bool {
+        self.breaks[offset + span] == self.breaks[offset]
+    }
+
+    // The window starting on row `offset`, before it is normalized.
+    fn raw_window(
+        &self,
+        offset: usize,
+        window_size: usize,
+        prediction_interval: usize,
+    ) -> BurnPriceDataItem {
+        let target = self.price_data[offset + window_size + prediction_interval].close;
+        BurnPriceDataItem::from_window(
+            &self.rows,
+            self.n_features,
+            &self.price_data,
+            offset..offset + window_size,
+            target,
+        )
+        .unwrap()
+    }
+
+    fn window(
+        &self,
+        offset: usize,
+        window_size: usize,
+        prediction_interval: usize,
+        normalizer: &Normalizer,
+        target_mode: TargetMode,
+    ) -> Option<NormBurnPriceDataItem> {
+        // the target is `prediction_interval` days after the day following the window.
+        self.raw_window(offset, window_size, prediction_interval)
+            .normalize(normalizer, target_mode, prediction_interval + 1)
+    }
+
+    // Whether the window starting on row `offset` can be read, without normalizing it. Every scheme scales
+    // finite values to finite values, so a window is only dropped for a gap, a missing value or its target.
+    fn is_valid(
+        &self,
+        offset: usize,
+        window_size: usize,
+        prediction_interval: usize,
+        target_mode: TargetMode,
+    ) -> bool {
+        self.is_contiguous(offset, window_size + prediction_interval)
+            && self
+                .raw_window(offset, window_size, prediction_interval)
+                .encode_target(target_mode, prediction_interval + 1)
+                .is_some()
+    }
+
+    // The first row on or after the date.
+    fn position(&self, date: NaiveDate) -> usize {
+        self.price_data
+            .iter()
+            .position(|item| item.date >= date)
+            .unwrap_or(self.price_data.len())
+    }
+
+    // The rows the windows of the company can start on, the target of the last one is the last row.
+    fn offsets(&self, window_size: usize, prediction_interval: usize) -> Range<usize> {
+        0..self
+            .price_data
+            .len()
+            .saturating_sub(window_size + prediction_interval)
     }
 }
 
+// The company and the row of the first day of a window.
+type WindowIndex = (usize, usize);
+
+/// The windows of every company, indexed by the company and the row the window starts on. Windows are built and
+/// normalized when they are read, so only the price data and the features of every company are kept in memory.
+#[derive(Clone)]
 pub struct TrainPriceDataSet {
-    pub dataset: InMemDataset<NormBurnPriceDataItem>,
+    series: Arc<Vec<CompanySeries>>,
+    index: Vec<WindowIndex>,
+    normalizer: Arc<Normalizer>,
+    window_size: usize,
+    prediction_interval: usize,
+    target_mode: TargetMode,
+}
+
+/// How the training windows are split into the windows the model is trained on and the windows it is tested on.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitMode {
+    /// Every company is split at the same cutoff date. Training windows end before the cutoff and test windows
+    /// start `window_size + prediction_interval + 1` trading days after it, so no window overlaps the other set.
+    #[default]
+    Chronological,
+    /// The windows of all companies in config order, the first `split_val` of them are used for training.
+    Sequential,
+}
+
+/// Which training windows make up every epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sampling {
+    /// Every training window once per epoch, in a shuffled order.
+    #[default]
+    Uniform,
+    /// Every company contributes the same number of windows to an epoch, so companies with a long history do not
+    /// dominate it. Windows are drawn with replacement.
+    BalancedCompany,
+    /// Every market regime contributes the same number of windows to an epoch. A window's regime is whether its
+    /// close rose over the window and whether its volatility is above the median of the training windows.
+    /// Windows are drawn with replacement.
+    BalancedRegime,
+}
+
+/// Reads the windows of a dataset in an order drawn for every epoch. A uniform epoch reads every window once in a
+/// shuffled order. A balanced epoch has as many windows as the dataset, each one picks a group and then a window
+/// of it at random, so that every group contributes equally. The windows of every epoch are drawn over the whole
+/// dataset from the seed and the epoch when it starts: the loader cuts the dataset into one contiguous slice per
+/// worker, and every slice mixes the companies whatever the number of workers.
+pub struct SampledDataSet {
+    dataset: TrainPriceDataSet,
+    sampling: Sampling,
+    // The indices of the dataset's windows in every group, no group is empty.
+    groups: Vec<Vec<usize>>,
+    seed: u64,
+    // The reads so far, every epoch reads every index once.
+    reads: AtomicUsize,
+    // The epoch being read and the window every index of it reads.
+    sampled: Mutex<(usize, Vec<usize>)>,
+}
+
+impl SampledDataSet {
+    pub fn new(dataset: TrainPriceDataSet, sampling: Sampling, seed: u64) -> Self {
+        let groups = dataset.groups(sampling);
+        let mut sampled = SampledDataSet {
+            dataset,
+            sampling,
+            groups,
+            seed,
+            reads: AtomicUsize::new(0),
+            sampled: Mutex::new((0, Vec::new())),
+        };
+        sampled.sampled = Mutex::new((0, sampled.sample(0)));
+        sampled
+    }
+
+    // The windows of the epoch, a permutation of the windows or drawn with replacement when balanced.
+    fn sample(&self, epoch: usize) -> Vec<usize> {
+        let mut rng = StdRng::seed_from_u64(self.seed.wrapping_add(epoch as u64));
+        match self.sampling {
+            Sampling::Uniform => {
+                let mut windows: Vec<usize> = (0..self.len()).collect();
+                windows.shuffle(&mut rng);
+                windows
+            }
+            Sampling::BalancedCompany | Sampling::BalancedRegime => (0..self.len())
+                .map(|_| {
+                    let group = &self.groups[rng.gen_range(0..self.groups.len())];
+                    group[rng.gen_range(0..group.len())]
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Dataset<NormBurnPriceDataItem> for SampledDataSet {
+    fn get(&self, idx: usize) -> Option<NormBurnPriceDataItem> {
+        let epoch = self.reads.fetch_add(1, Ordering::Relaxed) / self.len().max(1);
+        let window = {
+            let mut sampled = self.sampled.lock().unwrap();
+            if sampled.0 != epoch {
+                *sampled = (epoch, self.sample(epoch));
+            }
+            *sampled.1.get(idx)?
+        };
+        self.dataset.get(window)
+    }
+
+    fn len(&self) -> usize {
+        if self.groups.is_empty() {
+            0
+        } else {
+            self.dataset.len()
+        }
+    }
 }
 
 impl TrainPriceDataSet {
     fn new(
         companies: CompaniesPriceData,
         prediction_interval: usize,
-        split_val: f32,
-    ) -> (Self, Self) {
-        let items = TrainPriceDataSet::chunk(companies, prediction_interval);
+        config: &TrainPriceDataSetConfig,
+    ) -> (Self, Self, Normalizer) {
+        let series = TrainPriceDataSet::series(companies, config);
 
-        let split = (items.len() as f32 * split_val) as usize;
-        let train_dataset = items.iter().take(split).cloned().collect();
-        let test_dataset = items.iter().skip(split).cloned().collect();
+        let (train_index, test_index) = match config.split {
+            SplitMode::Chronological => {
+                TrainPriceDataSet::split_chronological(&series, prediction_interval, config)
+            }
+            SplitMode::Sequential => {
+                let index: Vec<WindowIndex> = series
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(company, series)| {
+                        series
+                            .offsets(config.window_size, prediction_interval)
+                            .map(move |offset| (company, offset))
+                    })
+                    .collect();
+                let split = (index.len() as f32 * config.split_val) as usize;
+                let test_index = index[split..].to_vec();
+                (index[..split].to_vec(), test_index)
+            }
+        };
+
+        return TrainPriceDataSet::from_index(
+            series,
+            train_index,
+            test_index,
+            prediction_interval,
+            config,
+        );
+    }
+
+    fn series(
+        companies: CompaniesPriceData,
+        config: &TrainPriceDataSetConfig,
+    ) -> Vec<CompanySeries> {
+        let calendar = TradingCalendar::new(config.calendar, &companies);
+        companies
+            .companies
+            .into_iter()
+            .map(|company| {
+                let rows = feature_rows(&config.features, &company.price_data);
+                CompanySeries::new(rows, config.features.len(), company.price_data, &calendar)
+            })
+            .collect()
+    }
+
+    // Fits the normalizer on the training windows and drops the windows that span a gap or cannot be read.
+    fn from_index(
+        series: Vec<CompanySeries>,
+        train_index: Vec<WindowIndex>,
+        test_index: Vec<WindowIndex>,
+        prediction_interval: usize,
+        config: &TrainPriceDataSetConfig,
+    ) -> (Self, Self, Normalizer) {
+        let window_size = config.window_size;
+
+        // the statistics are only fit on the training windows, so nothing leaks from the test windows.
+        let mut normalizer = Normalizer::new(config.normalization, config.features.clone());
+        normalizer.fit(train_index.iter().map(|(company, offset)| {
+            series[*company].raw_window(*offset, window_size, prediction_interval)
+        }));
+
+        let total = train_index.len() + test_index.len();
+        let valid = |index: Vec<WindowIndex>| -> Vec<WindowIndex> {
+            index
+                .into_iter()
+                .filter(|(company, offset)| {
+                    series[*company].is_valid(
+                        *offset,
+                        window_size,
+                        prediction_interval,
+                        config.target_mode,
+                    )
+                })
+                .collect()
+        };
+        let train_index = valid(train_index);
+        let test_index = valid(test_index);
+
+        let skipped = total - train_index.len() - test_index.len();
+        if skipped > 0 {
+            println!(
+                "Skipped {skipped} of {total} windows with missing values, gaps in the trading calendar or a target that cannot be encoded"
+            );
+        }
 
         let train = TrainPriceDataSet {
-            dataset: InMemDataset::new(train_dataset),
+            series: Arc::new(series),
+            index: train_index,
+            normalizer: Arc::new(normalizer.clone()),
+            window_size,
+            prediction_interval,
+            target_mode: config.target_mode,
         };
         let test = TrainPriceDataSet {
-            dataset: InMemDataset::new(test_dataset),
+            index: test_index,
+            ..train.clone()
+        };
+
+        return (train, test, normalizer);
+    }
+
+    // The indices of the windows in every group of the sampling scheme, empty groups are left out.
+    fn groups(&self, sampling: Sampling) -> Vec<Vec<usize>> {
+        let keys: Vec<usize> = match sampling {
+            Sampling::Uniform => vec![0; self.index.len()],
+            Sampling::BalancedCompany => self.index.iter().map(|(company, _)| *company).collect(),
+            Sampling::BalancedRegime => {
+                let contexts: Vec<(bool, f64)> = self
+                    .index
+                    .iter()
+                    .map(|(company, offset)| {
+                        let closes = self.series[*company]
+                            .raw_window(*offset, self.window_size, self.prediction_interval)
+                            .closes;
+                        let rising = closes.last() > closes.first();
+                        (rising, TargetContext::new(&closes, 1).volatility)
+                    })
+                    .collect();
+
+                let mut volatilities: Vec<f64> = contexts.iter().map(|(_, vol)| *vol).collect();
+                volatilities.sort_by(f64::total_cmp);
+                let median = volatilities
+                    .get(volatilities.len() / 2)
+                    .cloned()
+                    .unwrap_or(0.0);
+
+                contexts
+                    .iter()
+                    .map(|(rising, vol)| 2 * *rising as usize + (*vol > median) as usize)
+                    .collect()
+            }
         };
 
-        return (train, test);
+        let mut groups = vec![Vec::new(); keys.iter().max().map_or(0, |max| max + 1)];
+        for (idx, key) in keys.into_iter().enumerate() {
+            groups[key].push(idx);
+        }
+        groups.retain(|group| !group.is_empty());
+        return groups;
     }
 
-    fn chunk(
+    // Fold `fold` of `folds` consecutive date ranges is the test set. Training windows that overlap the fold are
+    // purged and the ones starting within the embargo after it are dropped.
+    fn fold(
         companies: CompaniesPriceData,
         prediction_interval: usize,
-    ) -> Vec<NormBurnPriceDataItem> {
-        let mut items = Vec::new();
+        config: &TrainPriceDataSetConfig,
+        folds: usize,
+        fold: usize,
+    ) -> (Self, Self, Normalizer) {
+        let series = TrainPriceDataSet::series(companies, config);
+        let (first, last) = match date_range(&series) {
+            Some(range) => range,
+            None => {
+                return TrainPriceDataSet::from_index(
+                    series,
+                    Vec::new(),
+                    Vec::new(),
+                    prediction_interval,
+                    config,
+                )
+            }
+        };
+
+        let days = (last - first).num_days() as u64 + 1;
+        let boundary = |idx: usize| first + Days::new(days * idx as u64 / folds as u64);
+        let (fold_start, fold_end) = (boundary(fold), boundary(fold + 1));
 
-        for company in companies.companies {
-            let mut i = 0;
+        // a window spans its days and the target day, `span` rows after its first day.
+        let span = config.window_size + prediction_interval;
+        let embargo = config.window_size + prediction_interval + 1;
 
-            let min_len = CHUNK_SIZE + prediction_interval + 1;
-            if company.price_data.len() < min_len {
-                continue;
+        let mut train_index = Vec::new();
+        let mut test_index = Vec::new();
+        for (company, series) in series.iter().enumerate() {
+            let offsets = series.offsets(config.window_size, prediction_interval);
+            let (start_idx, end_idx) = (series.position(fold_start), series.position(fold_end));
+
+            for offset in offsets {
+                if offset >= start_idx && offset + span < end_idx {
+                    test_index.push((company, offset));
+                } else if offset + span < start_idx || offset >= end_idx + embargo {
+                    train_index.push((company, offset));
+                }
             }
+        }
 
-            while i < company.price_data.len() - CHUNK_SIZE - prediction_interval {
-                let slice = &company.price_data[i..i + CHUNK_SIZE];
-                let data = slice.iter().map(|x| x.close).collect();
-                let target = company.price_data[i + CHUNK_SIZE + prediction_interval].close;
+        return TrainPriceDataSet::from_index(
+            series,
+            train_index,
+            test_index,
+            prediction_interval,
+            config,
+        );
+    }
 
-                let data = BurnPriceDataItem::from_data_vec(data, target)
-                    .unwrap()
-                    .normalize();
+    // Splits every company at the cutoff, the configured split date or the date `split_val` of the way through
+    // the training range.
+    fn split_chronological(
+        series: &[CompanySeries],
+        prediction_interval: usize,
+        config: &TrainPriceDataSetConfig,
+    ) -> (Vec<WindowIndex>, Vec<WindowIndex>) {
+        let cutoff = config.split_date.or_else(|| {
+            let (first, last) = date_range(series)?;
+            let days = ((last - first).num_days() as f32 * config.split_val) as u64;
+            first.checked_add_days(Days::new(days))
+        });
 
-                match data {
-                    Some(norm_data) => items.push(norm_data),
-                    None => (),
-                }
+        let cutoff = match cutoff {
+            Some(cutoff) => cutoff,
+            None => return (Vec::new(), Vec::new()),
+        };
+        println!("Splitting the training windows at {cutoff}");
+
+        // a training window's target is the day `train_span` rows after its first day.
+        let train_span = config.window_size + prediction_interval;
+        // the days the test windows keep away from the cutoff.
+        let embargo = config.window_size + prediction_interval + 1;
 
-                i += 1;
+        let mut train_index = Vec::new();
+        let mut test_index = Vec::new();
+        for (company, series) in series.iter().enumerate() {
+            let cutoff_idx = series.position(cutoff);
+            for offset in series.offsets(config.window_size, prediction_interval) {
+                if offset + train_span < cutoff_idx {
+                    train_index.push((company, offset));
+                } else if offset >= cutoff_idx + embargo {
+                    test_index.push((company, offset));
+                }
             }
         }
 
-        return items;
+        return (train_index, test_index);
     }
 }
 
+// The first and the last date of any company.
+fn date_range(series: &[CompanySeries]) -> Option<(NaiveDate, NaiveDate)> {
+    let first = series
+        .iter()
+        .filter_map(|series| series.price_data.first())
+        .map(|item| item.date)
+        .min()?;
+    let last = series
+        .iter()
+        .filter_map(|series| series.price_data.last())
+        .map(|item| item.date)
+        .max()?;
+    Some((first, last))
+}
+
 pub struct TrainPriceDataSetConfig {
     split_val: f32,
+    split: SplitMode,
+    split_date: Option<NaiveDate>,
+    window_size: usize,
+    features: Vec<Feature>,
+    target_mode: TargetMode,
+    normalization: Normalization,
+    // The calendar the days of every window have to be consecutive on.
+    calendar: CalendarKind,
 }
 
 impl TrainPriceDataSetConfig {
-    pub fn new(split_val: f32) -> Self {
-        Self { split_val }
+    pub fn new(experiment: &ExperimentConfig, calendar: CalendarKind) -> Self {
+        Self {
+            split_val: experiment.split_val,
+            split: experiment.split,
+            split_date: experiment.split_date,
+            window_size: experiment.window_size,
+            features: experiment.features.clone(),
+            target_mode: experiment.target,
+            normalization: experiment.normalization,
+            calendar,
+        }
+    }
+
+    /// Splits at `split_date` instead of the split date of the experiment, or at `split_val` when it is None.
+    pub fn with_split_date(mut self, split_date: Option<NaiveDate>) -> Self {
+        self.split_date = split_date;
+        self
     }
 
+    /// The training and test datasets, and the normalizer fit on the training windows.
     pub fn init(
         &self,
         companies: CompaniesPriceData,
         prediction_interval: usize,
-    ) -> (TrainPriceDataSet, TrainPriceDataSet) {
-        return TrainPriceDataSet::new(companies, prediction_interval, self.split_val);
+    ) -> (TrainPriceDataSet, TrainPriceDataSet, Normalizer) {
+        return TrainPriceDataSet::new(companies, prediction_interval, self);
+    }
+
+    /// The datasets of fold `fold` of a purged k-fold cross-validation with `folds` folds.
+    pub fn init_fold(
+        &self,
+        companies: CompaniesPriceData,
+        prediction_interval: usize,
+        folds: usize,
+        fold: usize,
+    ) -> (TrainPriceDataSet, TrainPriceDataSet, Normalizer) {
+        return TrainPriceDataSet::fold(companies, prediction_interval, self, folds, fold);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price_data::{testing, CompanyPriceData};
+
+    // `days` consecutive NYSE trading days from 2015-01-02 with a rising close, without the rows in `missing`.
+    fn company(symbol: &str, days: usize, missing: &[usize]) -> CompanyPriceData {
+        let mut date = NaiveDate::from_ymd_opt(2015, 1, 2).unwrap();
+        let mut price_data = Vec::new();
+        for idx in 0..days {
+            if !missing.contains(&idx) {
+                price_data.push(testing::item(date, 100.0 + idx as f64, 100));
+            }
+            date = TradingCalendar::Nyse.next_trading_day(date).unwrap();
+        }
+        testing::company(symbol, price_data)
+    }
+
+    fn companies(companies: Vec<CompanyPriceData>) -> CompaniesPriceData {
+        CompaniesPriceData { companies }
+    }
+
+    fn config(window_size: usize) -> TrainPriceDataSetConfig {
+        let experiment = ExperimentConfig {
+            window_size,
+            ..ExperimentConfig::default()
+        };
+        TrainPriceDataSetConfig::new(&experiment, CalendarKind::Nyse)
+    }
+
+    // Every window of every company, in order.
+    fn sequential(window_size: usize) -> TrainPriceDataSetConfig {
+        TrainPriceDataSetConfig {
+            split: SplitMode::Sequential,
+            split_val: 1.0,
+            ..config(window_size)
+        }
+    }
+
+    #[test]
+    fn windows_across_a_gap_are_skipped() {
+        let (train, _, _) = sequential(5).init(companies(vec![company("A", 40, &[20])]), 1);
+
+        // the 39 rows have 33 windows, each spans 7 rows and the ones starting on rows 14 to 19 cross the gap.
+        assert_eq!(train.len(), 27);
+        assert!(train
+            .index
+            .iter()
+            .all(|(_, offset)| !(14..=19).contains(offset)));
+    }
+
+    #[test]
+    fn days_the_calendar_has_no_trading_on_are_not_gaps() {
+        let mut a = company("A", 30, &[]);
+        // an extra row on a Saturday between two trading days.
+        let saturday = NaiveDate::from_ymd_opt(2015, 1, 10).unwrap();
+        let extra = PriceDataItem {
+            date: saturday,
+            ..a.price_data[5]
+        };
+        a.price_data.insert(6, extra);
+        let (train, _, _) = sequential(5).init(companies(vec![a]), 1);
+
+        assert_eq!(train.len(), 31 - 6);
+    }
+
+    fn chronological(window_size: usize, split_date: Option<NaiveDate>) -> TrainPriceDataSetConfig {
+        TrainPriceDataSetConfig {
+            split_date,
+            ..config(window_size)
+        }
+    }
+
+    #[test]
+    fn chronological_split_purges_and_embargoes_the_cutoff() {
+        let cutoff = NaiveDate::from_ymd_opt(2015, 3, 2).unwrap();
+        let config = chronological(5, Some(cutoff));
+        let series = TrainPriceDataSet::series(
+            companies(vec![company("A", 100, &[]), company("B", 60, &[])]),
+            &config,
+        );
+        let (train, test) = TrainPriceDataSet::split_chronological(&series, 1, &config);
+
+        // windows span 5 days, the day after and the target day 1 day later.
+        let (train_span, embargo) = (6, 7);
+        for (company, series) in series.iter().enumerate() {
+            let cutoff_idx = series.position(cutoff);
+            let train: Vec<usize> = train
+                .iter()
+                .filter(|(c, _)| *c == company)
+                .map(|(_, offset)| *offset)
+                .collect();
+            let test: Vec<usize> = test
+                .iter()
+                .filter(|(c, _)| *c == company)
+                .map(|(_, offset)| *offset)
+                .collect();
+
+            let last_train = *train.last().unwrap();
+            assert!(series.price_data[last_train + train_span].date < cutoff);
+            assert_eq!(last_train + train_span, cutoff_idx - 1);
+            assert_eq!(train, (0..=last_train).collect::<Vec<_>>());
+
+            let last_offset = series.offsets(5, 1).end;
+            assert_eq!(
+                test,
+                (cutoff_idx + embargo..last_offset).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn chronological_split_defaults_to_a_fraction_of_the_date_range() {
+        let config = TrainPriceDataSetConfig {
+            split_val: 0.5,
+            ..chronological(5, None)
+        };
+        let series = TrainPriceDataSet::series(companies(vec![company("A", 101, &[])]), &config);
+        let (train, test) = TrainPriceDataSet::split_chronological(&series, 1, &config);
+
+        // the cutoff is halfway between the first and the last date, not after half of the windows.
+        let (first, last) = date_range(&series).unwrap();
+        let cutoff = first + Days::new(((last - first).num_days() as f32 * 0.5) as u64);
+        let cutoff_idx = series[0].position(cutoff);
+        assert_eq!(train.last(), Some(&(0, cutoff_idx - 7)));
+        assert_eq!(test.first(), Some(&(0, cutoff_idx + 7)));
+    }
+
+    // The offsets of the windows of the first company in the dataset.
+    fn offsets(dataset: &TrainPriceDataSet) -> Vec<usize> {
+        dataset.index.iter().map(|(_, offset)| *offset).collect()
+    }
+
+    #[test]
+    fn folds_purge_and_embargo_the_test_range() {
+        let companies = companies(vec![company("A", 120, &[])]);
+        let series = TrainPriceDataSet::series(companies.clone(), &config(5));
+        let (first, last) = date_range(&series).unwrap();
+        let days = (last - first).num_days() as u64 + 1;
+        let position = |fold: u64| series[0].position(first + Days::new(days * fold / 4));
+        let last_offset = series[0].offsets(5, 1).end;
+
+        // windows span 5 days, the day after and the target day 1 day later.
+        let (span, embargo) = (6, 7);
+        for fold in 0..4 {
+            let (train, test, _) = config(5).init_fold(companies.clone(), 1, 4, fold);
+            let (start_idx, end_idx) = (position(fold as u64), position(fold as u64 + 1));
+
+            assert_eq!(
+                offsets(&test),
+                (start_idx..end_idx.saturating_sub(span)).collect::<Vec<_>>()
+            );
+            let expected: Vec<usize> = (0..start_idx.saturating_sub(span))
+                .chain(end_idx + embargo..last_offset)
+                .collect();
+            assert_eq!(offsets(&train), expected);
+        }
+    }
+
+    #[test]
+    fn offsets_end_at_the_last_target() {
+        let config = config(5);
+        let series = TrainPriceDataSet::series(
+            companies(vec![company("A", 30, &[]), company("B", 7, &[])]),
+            &config,
+        );
+
+        // the last window's target, 5 + 2 rows after its first day, is the last row.
+        let offsets = series[0].offsets(5, 2);
+        assert_eq!(offsets, 0..23);
+        assert_eq!(offsets.end - 1 + 5 + 2, series[0].price_data.len() - 1);
+        assert!(series[1].offsets(5, 2).is_empty());
+    }
+
+    #[test]
+    fn windows_are_read_like_the_chunked_windows() {
+        let companies = companies(vec![company("A", 30, &[]), company("B", 25, &[])]);
+        let (window_size, prediction_interval) = (5, 2);
+        let (train, _, normalizer) =
+            sequential(window_size).init(companies.clone(), prediction_interval);
+
+        // every window of every company in order, built eagerly the way the datasets used to be.
+        let mut chunked = Vec::new();
+        for company in companies.iter() {
+            let features = ExperimentConfig::default().features;
+            let rows = feature_rows(&features, &company.price_data);
+            let last_start = company.price_data.len() - window_size - prediction_interval;
+            for i in 0..last_start {
+                let target = company.price_data[i + window_size + prediction_interval].close;
+                let item = BurnPriceDataItem::from_window(
+                    &rows,
+                    features.len(),
+                    &company.price_data,
+                    i..i + window_size,
+                    target,
+                )
+                .unwrap();
+                chunked.extend(item.normalize(
+                    &normalizer,
+                    TargetMode::default(),
+                    prediction_interval + 1,
+                ));
+            }
+        }
+
+        assert_eq!(train.len(), chunked.len());
+        for (idx, expected) in chunked.iter().enumerate() {
+            let item = train.get(idx).unwrap();
+            assert_eq!(item.data, expected.data);
+            assert_eq!(item.closes, expected.closes);
+            assert_eq!(item.target, expected.target);
+        }
+        assert!(train.get(chunked.len()).is_none());
+    }
+
+    // A long and a short company, so that the uniform sampling is dominated by the long one.
+    fn unbalanced() -> TrainPriceDataSet {
+        let companies = companies(vec![company("A", 200, &[]), company("B", 40, &[])]);
+        sequential(5).init(companies, 1).0
+    }
+
+    #[test]
+    fn balanced_groups_have_equal_expected_weight() {
+        for sampling in [Sampling::BalancedCompany, Sampling::BalancedRegime] {
+            let balanced = SampledDataSet::new(unbalanced(), sampling, 7);
+            assert!(balanced.groups.len() > 1);
+            assert_eq!(balanced.len(), 228);
+
+            let mut draws = vec![0usize; balanced.groups.len()];
+            for epoch in 0..20 {
+                for window in balanced.sample(epoch) {
+                    let group = balanced
+                        .groups
+                        .iter()
+                        .position(|group| group.contains(&window))
+                        .unwrap();
+                    draws[group] += 1;
+                }
+            }
+
+            let total = draws.iter().sum::<usize>() as f64;
+            for count in draws {
+                let weight = count as f64 / total;
+                assert!(
+                    (weight - 1.0 / balanced.groups.len() as f64).abs() < 0.03,
+                    "{sampling:?} drew a group with weight {weight}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn balanced_epochs_are_reproducible() {
+        let a = SampledDataSet::new(unbalanced(), Sampling::BalancedCompany, 7);
+        let b = SampledDataSet::new(unbalanced(), Sampling::BalancedCompany, 7);
+        let len = a.len();
+
+        // the windows, told apart by their first close, do not depend on the order the workers read them in.
+        let mut epochs = Vec::new();
+        for _ in 0..3 {
+            let read_a: Vec<f64> = (0..len).map(|idx| a.get(idx).unwrap().closes[0]).collect();
+            let mut read_b: Vec<f64> = (0..len)
+                .rev()
+                .map(|idx| b.get(idx).unwrap().closes[0])
+                .collect();
+            read_b.reverse();
+            assert_eq!(read_a, read_b);
+            epochs.push(read_a);
+        }
+
+        // every epoch draws other windows.
+        assert_ne!(epochs[0], epochs[1]);
+        assert_ne!(epochs[1], epochs[2]);
+        assert_ne!(
+            a.sample(0),
+            SampledDataSet::new(unbalanced(), Sampling::BalancedCompany, 8).sample(0)
+        );
+    }
+
+    #[test]
+    fn windows_are_sliced_from_the_flat_rows() {
+        let config = TrainPriceDataSetConfig {
+            features: vec![Feature::Close, Feature::Volume],
+            ..config(2)
+        };
+        let series = TrainPriceDataSet::series(companies(vec![company("A", 10, &[])]), &config);
+
+        assert_eq!(series[0].rows.len(), 10 * 2);
+        let window = series[0].raw_window(3, 2, 1);
+        assert_eq!(window.data, vec![103.0, 100.0, 104.0, 100.0]);
+        assert_eq!(window.closes, vec![103.0, 104.0]);
+        assert_eq!(window.target, 106.0);
+    }
+
+    #[test]
+    fn every_worker_slice_mixes_the_companies() {
+        let symbols = ["A", "B", "C", "D", "E", "F"];
+        let companies = companies(
+            symbols
+                .iter()
+                .map(|symbol| company(symbol, 60, &[]))
+                .collect(),
+        );
+        let sampled = SampledDataSet::new(sequential(5).init(companies, 1).0, Sampling::Uniform, 7);
+        let len = sampled.len();
+
+        for epoch in 0..3 {
+            let windows = sampled.sample(epoch);
+
+            // a uniform epoch reads every window once.
+            let mut sorted = windows.clone();
+            sorted.sort();
+            assert_eq!(sorted, (0..len).collect::<Vec<_>>());
+
+            // the loader cuts the dataset into one contiguous slice for each of 4 workers, a batch of 32 windows
+            // from any slice holds most of the companies.
+            for slice in windows.chunks(len / 4) {
+                let mut companies: Vec<usize> = slice[..32]
+                    .iter()
+                    .map(|window| sampled.dataset.index[*window].0)
+                    .collect();
+                companies.sort();
+                companies.dedup();
+                assert!(companies.len() >= 5, "a batch held companies {companies:?}");
+            }
+        }
+        assert_ne!(sampled.sample(0), sampled.sample(1));
     }
 }
@@ -0,0 +1,218 @@
+use std::fs::File;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    baseline::Baseline,
+    cross_validation::CrossValidationConfig,
+    data_loader::{Sampling, SplitMode},
+    normalization::Normalization,
+    target::TargetMode,
+    walk_forward::{WalkForwardConfig, WalkForwardWindow},
+    Architecture, Feature,
+};
+
+/// Every hyperparameter of a training run and its backtest, read from the `experiment` section of the
+/// configuration file. Keys that are left out keep their default value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExperimentConfig {
+    // The number of trading days the model sees for every prediction.
+    pub window_size: usize,
+    // The series the model sees for every day of the window.
+    pub features: Vec<Feature>,
+    // How the features of every window are scaled.
+    pub normalization: Normalization,
+    pub hidden_size: usize,
+    pub architecture: Architecture,
+    // Feeds the window to the LSTM one day at a time instead of as a single flattened step.
+    pub sequence: bool,
+    pub num_layers: usize,
+    // Dropout between the layers of the model.
+    pub dropout: f64,
+    // Attention heads of the transformer architecture.
+    pub num_heads: usize,
+    // Kernel size of the TCN architecture.
+    pub kernel_size: usize,
+    pub num_epochs: usize,
+    pub batch_size: usize,
+    pub num_workers: usize,
+    pub seed: u64,
+    pub learning_rate: f64,
+    // How the training windows are split into training and validation windows.
+    pub split: SplitMode,
+    // The cutoff of the chronological split, `split_val` of the way through the training range when left out.
+    // Walk-forward folds ignore it and validate on the days before every fold.
+    pub split_date: Option<NaiveDate>,
+    // Where the training data is split, the fraction of its date range before the cutoff of the chronological
+    // split, or the fraction of the training windows used for training by the sequential split.
+    pub split_val: f32,
+    // Which training windows make up every epoch.
+    pub sampling: Sampling,
+    // The number of trading days after the window that the model predicts.
+    pub prediction_interval: usize,
+    // What the model predicts about the close at the end of the prediction interval.
+    pub target: TargetMode,
+    // The number of days a position is held by the backtest before it is sold.
+    pub hold_length: usize,
+    pub start_balance: f64,
+    // Trivial predictors whose validation loss and backtest are reported next to the trained model.
+    pub baselines: Vec<Baseline>,
+    // How the `walk-forward` command retrains the model through the validation range.
+    pub walk_forward: WalkForwardConfig,
+    // How the `cv` command cross-validates the experiment and its variants.
+    pub cross_validation: CrossValidationConfig,
+}
+
+impl Default for ExperimentConfig {
+    fn default() -> Self {
+        ExperimentConfig {
+            window_size: 32,
+            features: vec![Feature::Close],
+            normalization: Normalization::MinMax,
+            hidden_size: 64,
+            architecture: Architecture::Lstm,
+            sequence: false,
+            num_layers: 1,
+            dropout: 0.0,
+            num_heads: 4,
+            kernel_size: 3,
+            num_epochs: 10,
+            batch_size: 64,
+            num_workers: 4,
+            seed: 42,
+            learning_rate: 0.0001,
+            split: SplitMode::Chronological,
+            split_date: None,
+            split_val: 0.9,
+            sampling: Sampling::Uniform,
+            prediction_interval: 1,
+            target: TargetMode::NormalizedPrice,
+            hold_length: 1,
+            start_balance: 10000.0,
+            baselines: Vec::new(),
+            walk_forward: WalkForwardConfig::default(),
+            cross_validation: CrossValidationConfig::default(),
+        }
+    }
+}
+
+impl ExperimentConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window_size < 2 {
+            return Err(format!(
+                "experiment.window_size must be at least 2, found {}",
+                self.window_size
+            ));
+        }
+        if self.features.is_empty() {
+            return Err("experiment.features must list at least one feature".to_string());
+        }
+        for (name, value) in [
+            ("hidden_size", self.hidden_size),
+            ("num_layers", self.num_layers),
+            ("num_epochs", self.num_epochs),
+            ("batch_size", self.batch_size),
+            ("num_workers", self.num_workers),
+            ("hold_length", self.hold_length),
+            ("walk_forward.step_days", self.walk_forward.step_days),
+            (
+                "walk_forward.fine_tune_epochs",
+                self.walk_forward.fine_tune_epochs,
+            ),
+        ] {
+            if value == 0 {
+                return Err(format!("experiment.{name} must be greater than 0"));
+            }
+        }
+        if self.architecture == Architecture::Transformer
+            && (self.num_heads == 0 || !self.hidden_size.is_multiple_of(self.num_heads))
+        {
+            return Err(format!(
+                "experiment.num_heads must divide experiment.hidden_size, found {} heads for {}",
+                self.num_heads, self.hidden_size
+            ));
+        }
+        if self.architecture == Architecture::Tcn && self.kernel_size < 2 {
+            return Err(format!(
+                "experiment.kernel_size must be at least 2, found {}",
+                self.kernel_size
+            ));
+        }
+        if !(0.0..1.0).contains(&self.dropout) {
+            return Err(format!(
+                "experiment.dropout must be at least 0 and below 1, found {}",
+                self.dropout
+            ));
+        }
+        if !self.learning_rate.is_finite() || self.learning_rate <= 0.0 {
+            return Err(format!(
+                "experiment.learning_rate must be a positive number, found {}",
+                self.learning_rate
+            ));
+        }
+        if !(self.split_val > 0.0 && self.split_val < 1.0) {
+            return Err(format!(
+                "experiment.split_val must be between 0 and 1, found {}",
+                self.split_val
+            ));
+        }
+        if !self.start_balance.is_finite() || self.start_balance <= 0.0 {
+            return Err(format!(
+                "experiment.start_balance must be a positive number, found {}",
+                self.start_balance
+            ));
+        }
+        // an expanding window trains on every day before the fold and ignores `train_days`.
+        if self.walk_forward.window == WalkForwardWindow::Rolling
+            && self.walk_forward.train_days <= self.window_size + self.prediction_interval
+        {
+            return Err(format!(
+                "experiment.walk_forward.train_days must be greater than window_size + prediction_interval, found {}",
+                self.walk_forward.train_days
+            ));
+        }
+        if self.cross_validation.folds < 2 {
+            return Err(format!(
+                "experiment.cross_validation.folds must be at least 2, found {}",
+                self.cross_validation.folds
+            ));
+        }
+        self.cross_validation.experiments(self)?;
+
+        Ok(())
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let file = File::create(path).map_err(|err| err.to_string())?;
+        serde_json::to_writer_pretty(file, self).map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn train_days_only_bound_a_rolling_window() {
+        let mut experiment = ExperimentConfig::default();
+        experiment.walk_forward.train_days = experiment.window_size;
+        assert!(experiment.validate().is_ok());
+
+        experiment.walk_forward.window = WalkForwardWindow::Rolling;
+        assert!(experiment.validate().is_err());
+    }
+
+    #[test]
+    fn loaders_need_a_worker() {
+        let experiment = ExperimentConfig {
+            num_workers: 0,
+            ..ExperimentConfig::default()
+        };
+        assert_eq!(
+            experiment.validate(),
+            Err("experiment.num_workers must be greater than 0".to_string())
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::price_data::{Indicator, PriceDataItem};
+
+/// A single input series of the model, computed for every row of a company's price data.
+/// Features are configured by name, `"close"`, `"log_return"` or an indicator such as `"rsi_14"`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Feature {
+    Close,
+    Open,
+    High,
+    Low,
+    /// The natural log of the close over the previous close.
+    LogReturn,
+    Volume,
+    /// The high to low range as a fraction of the close.
+    Range,
+    Indicator(Indicator),
+}
+
+impl TryFrom<String> for Feature {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        let feature = match name.as_str() {
+            "close" => Feature::Close,
+            "open" => Feature::Open,
+            "high" => Feature::High,
+            "low" => Feature::Low,
+            "log_return" => Feature::LogReturn,
+            "volume" => Feature::Volume,
+            "range" => Feature::Range,
+            name => Feature::Indicator(
+                Indicator::from_name(name).ok_or(format!("unknown feature \"{name}\""))?,
+            ),
+        };
+        Ok(feature)
+    }
+}
+
+impl From<Feature> for String {
+    fn from(feature: Feature) -> Self {
+        feature.to_string()
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Feature::Close => write!(f, "close"),
+            Feature::Open => write!(f, "open"),
+            Feature::High => write!(f, "high"),
+            Feature::Low => write!(f, "low"),
+            Feature::LogReturn => write!(f, "log_return"),
+            Feature::Volume => write!(f, "volume"),
+            Feature::Range => write!(f, "range"),
+            Feature::Indicator(indicator) => write!(f, "{indicator}"),
+        }
+    }
+}
+
+impl Feature {
+    /// The value of the feature for every row, each value only depends on its own row and the rows before it.
+    /// Rows without enough history to compute a value are NaN.
+    pub fn series(&self, price_data: &[PriceDataItem]) -> Vec<f64> {
+        match self {
+            Feature::Close => price_data.iter().map(|item| item.close).collect(),
+            Feature::Open => price_data.iter().map(|item| item.open).collect(),
+            Feature::High => price_data.iter().map(|item| item.high).collect(),
+            Feature::Low => price_data.iter().map(|item| item.low).collect(),
+            Feature::LogReturn => std::iter::once(f64::NAN)
+                .chain(
+                    price_data
+                        .windows(2)
+                        .map(|pair| (pair[1].close / pair[0].close).ln()),
+                )
+                .take(price_data.len())
+                .collect(),
+            Feature::Volume => price_data.iter().map(|item| item.volume as f64).collect(),
+            Feature::Range => price_data
+                .iter()
+                .map(|item| (item.high - item.low) / item.close)
+                .collect(),
+            Feature::Indicator(indicator) => indicator.compute(price_data),
+        }
+    }
+
+    /// Whether the feature is a price, in the same unit as the close.
+    pub fn is_price(&self) -> bool {
+        matches!(
+            self,
+            Feature::Close
+                | Feature::Open
+                | Feature::High
+                | Feature::Low
+                | Feature::Indicator(Indicator::Sma(_) | Indicator::Ema(_))
+        )
+    }
+
+    /// The number of rows before the first row that has a value.
+    pub fn lookback(&self) -> usize {
+        match self {
+            Feature::LogReturn => 1,
+            Feature::Indicator(indicator) => indicator.lookback(),
+            _ => 0,
+        }
+    }
+}
+
+/// The rows of extra history needed in front of a window so that every feature has a value on its first row.
+pub fn lookback(features: &[Feature]) -> usize {
+    features
+        .iter()
+        .map(|feature| feature.lookback())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Computes every feature over the price data, one row of `features.len()` values per row of price data. The
+/// rows are stored one after the other in a single vector.
+pub fn feature_rows(features: &[Feature], price_data: &[PriceDataItem]) -> Vec<f64> {
+    let columns: Vec<Vec<f64>> = features
+        .iter()
+        .map(|feature| feature.series(price_data))
+        .collect();
+
+    (0..price_data.len())
+        .flat_map(|idx| columns.iter().map(move |column| column[idx]))
+        .collect()
+}
@@ -69,15 +69,21 @@ impl Book {
             let current_price = company
                 .price_data
                 .iter()
-                .position(|price_data| price_data.date >= current_date);
+                .position(|price_data| price_data.date >= current_date && !price_data.is_missing());
 
             match current_price {
                 Some(idx) => {
                     value += holding.value(company.price_data[idx].close);
                 }
                 None => {
-                    if current_date > company.price_data.last().unwrap().date {
-                        value += holding.value(company.price_data.last().unwrap().close);
+                    let last = company
+                        .price_data
+                        .iter()
+                        .rev()
+                        .find(|price_data| !price_data.is_missing())
+                        .unwrap();
+                    if current_date > last.date {
+                        value += holding.value(last.close);
                     } else {
                         panic!();
                     }
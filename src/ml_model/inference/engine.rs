@@ -1,13 +1,17 @@
 use burn::data::dataloader::batcher::Batcher;
 use burn::tensor::backend::Backend;
-use chrono::{Datelike, Days, NaiveDate, Weekday};
+use chrono::{Days, NaiveDate};
 
 use crate::{
     ml_model::{
-        data::PriceDataBatcher, data_loader::BurnPriceDataItem, model::Model,
-        NormCompanyPriceDataBatch,
+        data::PriceDataBatcher,
+        data_loader::BurnPriceDataItem,
+        features::{feature_rows, lookback},
+        model::Predictor,
+        normalization::Normalizer,
+        ExperimentConfig, Feature, NormCompanyPriceDataBatch, TargetMode,
     },
-    price_data::CompaniesPriceData,
+    price_data::{CompaniesPriceData, TradingCalendar},
 };
 
 use super::book::{Book, Holding};
@@ -23,8 +27,18 @@ pub struct StrategyEngine<'a> {
 impl<'a> StrategyEngine<'a> {
     pub fn new(start_date: NaiveDate, end_date: NaiveDate, strategy: Strategy) -> StrategyEngine {
         let book = Book::new(strategy.start_balance);
+
+        // the backtest starts on the first trading day on or after the start date.
+        let date = match strategy.calendar.is_trading_day(start_date) {
+            true => start_date,
+            false => strategy
+                .calendar
+                .next_trading_day(start_date)
+                .unwrap_or(end_date),
+        };
+
         StrategyEngine {
-            date: start_date,
+            date,
             end_date,
             book,
             strategy,
@@ -32,7 +46,11 @@ impl<'a> StrategyEngine<'a> {
         }
     }
 
-    pub fn step_day<B: Backend>(&mut self, batcher: &PriceDataBatcher<B>, model: &Model<B>) {
+    pub fn step_day<B: Backend, M: Predictor<B>>(
+        &mut self,
+        batcher: &PriceDataBatcher<B>,
+        model: &M,
+    ) {
         // this need something to adapt for larger datasets, but sqrt limits the selections too much.
         let predictions = self.predict_prices(batcher, model);
 
@@ -63,12 +81,19 @@ impl<'a> StrategyEngine<'a> {
             .companies
             .iter()
             .find(|company| *company == &selection.company)
-            .unwrap()
-            .price_data
-            .iter()
-            .find(|price_data| price_data.date == self.date)
-            .unwrap()
-            .close;
+            .and_then(|company| {
+                company
+                    .price_data
+                    .iter()
+                    .find(|price_data| price_data.date == self.date && !price_data.is_missing())
+            })
+            .map(|price_data| price_data.close);
+
+        // the company has no price today, so it cannot be bought.
+        let current_price = match current_price {
+            Some(current_price) => current_price,
+            None => return,
+        };
 
         let weight = 1.0 / count as f64;
         let share_count = (self.strategy.start_balance * weight) / current_price;
@@ -97,55 +122,61 @@ impl<'a> StrategyEngine<'a> {
                     .strategy
                     .companies
                     .iter()
-                    .find(|company| company.symbol == transaction.company.symbol)
-                    .unwrap();
-
-                let curr_price_idx = company
-                    .price_data
-                    .iter()
-                    .position(|price_data| price_data.date >= self.date)
-                    .unwrap();
-
-                let sale_date = company.price_data[curr_price_idx].date;
-                let current_price = company.price_data[curr_price_idx].close;
-
-                self.book
-                    .sell(transaction.clone(), current_price, sale_date);
+                    .find(|company| company.symbol == transaction.company.symbol);
+
+                // a company without prices from today on delisted, it is sold at its last known price.
+                let sale = company.and_then(|company| {
+                    company
+                        .price_data
+                        .iter()
+                        .find(|price_data| price_data.date >= self.date && !price_data.is_missing())
+                        .map(|price_data| (price_data.close, price_data.date))
+                        .or_else(|| {
+                            company
+                                .price_data
+                                .iter()
+                                .rev()
+                                .find(|price_data| !price_data.is_missing())
+                                .map(|price_data| (price_data.close, self.date))
+                        })
+                });
+
+                if let Some((current_price, sale_date)) = sale {
+                    self.book
+                        .sell(transaction.clone(), current_price, sale_date);
+                }
             }
         });
     }
 
-    fn predict_prices<B: Backend>(
+    fn predict_prices<B: Backend, M: Predictor<B>>(
         &self,
         batcher: &PriceDataBatcher<B>,
-        model: &Model<B>,
+        model: &M,
     ) -> Vec<NormCompanyPriceDataBatch<B>> {
         let mut batches: Vec<NormCompanyPriceDataBatch<B>> =
             self.batch_set(batcher, self.date, self.strategy.companies);
 
         batches
             .iter_mut()
-            .for_each(|batch| batch.data = model.forward(batch.data.clone()));
+            .for_each(|batch| batch.prediction = Some(model.predict(&batch.batch)));
 
-        batches.sort_by(|batch1, batch2| {
-            let val1 = batch1.norm_delta();
-            let val2 = batch2.norm_delta();
-            val1.partial_cmp(&val2).unwrap()
-        });
+        // the highest expected return first, a prediction that is not a number last.
+        let rank = |batch: &NormCompanyPriceDataBatch<B>| match batch.expected_return() {
+            value if value.is_nan() => f64::NEG_INFINITY,
+            value => value,
+        };
+        batches.sort_by(|batch1, batch2| rank(batch2).total_cmp(&rank(batch1)));
 
         return batches;
     }
 
     fn incr_date(&mut self) {
-        let mut new_date = self.date.succ_opt().unwrap();
-        let dow = new_date.weekday();
-        if dow == Weekday::Sat {
-            new_date = new_date.succ_opt().unwrap().succ_opt().unwrap();
-        }
-        if dow == Weekday::Sun {
-            new_date = new_date.succ_opt().unwrap();
-        }
-        self.date = new_date;
+        self.date = self
+            .strategy
+            .calendar
+            .next_trading_day(self.date)
+            .unwrap_or(self.end_date);
     }
 
     fn batch_set<B: Backend>(
@@ -154,19 +185,31 @@ impl<'a> StrategyEngine<'a> {
         curr_date: NaiveDate,
         companies: &CompaniesPriceData,
     ) -> Vec<NormCompanyPriceDataBatch<B>> {
-        let price_data = companies.fetch_validate_data_set(curr_date);
+        let window_size = self.strategy.window_size;
+        // the features on the first day of the window need the days before it.
+        let history = window_size + lookback(&self.strategy.features);
+        let price_data = companies.fetch_validate_data_set(curr_date, history);
 
         let mut batches = Vec::new();
 
         for item in price_data.iter() {
-            let close_data = item.search_data.iter().map(|x| x.close).collect();
+            let rows = feature_rows(&self.strategy.features, &item.search_data);
 
-            // To maintain the context of the last close price, we use target as the placeholder to hold the value.
-            // because this value is not getting re-read into the model it should not polute the data.
+            // the future close is unknown, the last close is a placeholder so the window can be normalized.
             let target = item.search_data.last().unwrap().close;
-            let data = BurnPriceDataItem::from_data_vec(close_data, target)
-                .unwrap()
-                .normalize();
+            let data = BurnPriceDataItem::from_window(
+                &rows,
+                self.strategy.features.len(),
+                &item.search_data,
+                history - window_size..history,
+                target,
+            )
+            .unwrap()
+            .normalize(
+                &self.strategy.normalizer,
+                self.strategy.target_mode,
+                self.strategy.horizon,
+            );
 
             match data {
                 Some(data) => {
@@ -181,8 +224,16 @@ impl<'a> StrategyEngine<'a> {
     }
 }
 
+#[derive(Clone)]
 pub struct Strategy<'a> {
     pub companies: &'a CompaniesPriceData,
+    pub calendar: &'a TradingCalendar,
+    window_size: usize,
+    features: Vec<Feature>,
+    normalizer: Normalizer,
+    target_mode: TargetMode,
+    // The days between the last day of the window and the predicted close.
+    horizon: usize,
     hold_for: usize,
     start_balance: f64,
 }
@@ -190,13 +241,54 @@ pub struct Strategy<'a> {
 impl Strategy<'_> {
     pub fn new<'a>(
         companies: &'a CompaniesPriceData,
-        hold_for: usize,
-        start_balance: f64,
+        calendar: &'a TradingCalendar,
+        experiment: &ExperimentConfig,
+        normalizer: Normalizer,
     ) -> Strategy<'a> {
         Strategy {
             companies,
-            hold_for,
-            start_balance,
+            calendar,
+            window_size: experiment.window_size,
+            features: experiment.features.clone(),
+            normalizer,
+            target_mode: experiment.target,
+            horizon: experiment.prediction_interval + 1,
+            hold_for: experiment.hold_length,
+            start_balance: experiment.start_balance,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price_data::testing;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2020, 1, day).unwrap()
+    }
+
+    #[test]
+    fn delisted_holdings_are_sold_at_their_last_price() {
+        let price_data = vec![
+            testing::item(date(2), 10.0, 1),
+            testing::item(date(3), 12.0, 1),
+        ];
+        let companies = CompaniesPriceData {
+            companies: vec![testing::company("A", price_data)],
+        };
+        let calendar = TradingCalendar::Nyse;
+        let experiment = ExperimentConfig::default();
+        let normalizer = Normalizer::new(experiment.normalization, experiment.features.clone());
+        let strategy = Strategy::new(&companies, &calendar, &experiment, normalizer);
+
+        let mut engine = StrategyEngine::new(date(7), date(10), strategy);
+        let holding = Holding::new(companies.companies[0].company(), date(2), 10.0, 5);
+        engine.book.purchase(holding, 10.0, date(2));
+        let balance = engine.book.balance;
+
+        engine.sell_stale_positions();
+        assert!(engine.book.holdings.is_empty());
+        assert_eq!(engine.book.balance, balance + 5.0 * 12.0);
+    }
+}
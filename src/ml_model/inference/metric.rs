@@ -1,7 +1,7 @@
 use chrono::{Datelike, Months, NaiveDate};
 use plotly::{common::Mode, layout::Annotation, HeatMap, Layout, Plot, Scatter};
 
-use crate::price_data::{CompaniesPriceData, Company};
+use crate::price_data::{CompaniesPriceData, Company, TradingCalendar};
 
 use super::{
     book::{Side, Transaction},
@@ -21,6 +21,127 @@ pub fn plot_model_output(model_output: Vec<(NaiveDate, f64)>) {
     profit_plot.show();
 }
 
+pub struct PerformanceReport {
+    pub start_value: f64,
+    pub end_value: f64,
+    pub total_return: f64,
+    pub annualized_return: f64,
+    pub annualized_volatility: f64,
+    pub sharpe_ratio: f64,
+    pub max_drawdown: f64,
+    pub trading_days: usize,
+}
+
+impl PerformanceReport {
+    /// Summarizes a value history, returns are annualized by the number of trading days per year in the calendar.
+    pub fn new(
+        value_history: &[(NaiveDate, f64)],
+        calendar: &TradingCalendar,
+    ) -> PerformanceReport {
+        let (first, last) = match (value_history.first(), value_history.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => {
+                return PerformanceReport {
+                    start_value: 0.0,
+                    end_value: 0.0,
+                    total_return: 0.0,
+                    annualized_return: 0.0,
+                    annualized_volatility: 0.0,
+                    sharpe_ratio: 0.0,
+                    max_drawdown: 0.0,
+                    trading_days: 0,
+                }
+            }
+        };
+
+        let trading_days = calendar.trading_days(first.0, last.0).len();
+        let years = (last.0 - first.0).num_days() as f64 / 365.25;
+        let days_per_year = match years > 0.0 {
+            true => trading_days as f64 / years,
+            false => 252.0,
+        };
+
+        let returns: Vec<f64> = value_history
+            .windows(2)
+            .map(|pair| pair[1].1 / pair[0].1 - 1.0)
+            .filter(|change| change.is_finite())
+            .collect();
+        let mean = returns.iter().sum::<f64>() / returns.len().max(1) as f64;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>()
+            / (returns.len().max(2) - 1) as f64;
+
+        let mut peak = f64::NEG_INFINITY;
+        let mut max_drawdown: f64 = 0.0;
+        for (_, value) in value_history {
+            peak = peak.max(*value);
+            max_drawdown = max_drawdown.max(1.0 - value / peak);
+        }
+
+        let total_return = last.1 / first.1 - 1.0;
+        let annualized_volatility = variance.sqrt() * days_per_year.sqrt();
+
+        PerformanceReport {
+            start_value: first.1,
+            end_value: last.1,
+            total_return,
+            annualized_return: match years > 0.0 {
+                true => (1.0 + total_return).powf(1.0 / years) - 1.0,
+                false => total_return,
+            },
+            annualized_volatility,
+            sharpe_ratio: match annualized_volatility > 0.0 {
+                true => mean * days_per_year / annualized_volatility,
+                false => 0.0,
+            },
+            max_drawdown,
+            trading_days,
+        }
+    }
+}
+
+impl std::fmt::Display for PerformanceReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Value: {start:.2} -> {end:.2} over {days} trading days",
+            start = self.start_value,
+            end = self.end_value,
+            days = self.trading_days
+        )?;
+        writeln!(f, "Total return: {:.2}%", self.total_return * 100.0)?;
+        writeln!(
+            f,
+            "Annualized return: {:.2}%",
+            self.annualized_return * 100.0
+        )?;
+        writeln!(
+            f,
+            "Annualized volatility: {:.2}%",
+            self.annualized_volatility * 100.0
+        )?;
+        writeln!(f, "Sharpe ratio: {:.2}", self.sharpe_ratio)?;
+        write!(f, "Max drawdown: {:.2}%", self.max_drawdown * 100.0)
+    }
+}
+
+/// Prints one row per backtest, so the model can be compared against the baselines at a glance.
+pub fn print_comparison(reports: &[(String, PerformanceReport)]) {
+    println!(
+        "{:<24}{:>12}{:>12}{:>12}{:>8}{:>12}",
+        "", "return", "annualized", "volatility", "sharpe", "drawdown"
+    );
+    for (name, report) in reports {
+        println!(
+            "{name:<24}{:>11.2}%{:>11.2}%{:>11.2}%{:>8.2}{:>11.2}%",
+            report.total_return * 100.0,
+            report.annualized_return * 100.0,
+            report.annualized_volatility * 100.0,
+            report.sharpe_ratio,
+            report.max_drawdown * 100.0
+        );
+    }
+}
+
 pub struct BiasWindow {
     pub year: i32,
     pub month: u32,
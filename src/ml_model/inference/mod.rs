@@ -5,40 +5,121 @@ mod metric;
 use burn::{record::CompactRecorder, tensor::backend::Backend};
 use chrono::NaiveDate;
 use engine::{Strategy, StrategyEngine};
-use metric::{plot_company_bias, plot_model_output};
+use metric::{plot_company_bias, plot_model_output, print_comparison, PerformanceReport};
+use std::path::Path;
 
 use crate::ml_model::data::PriceDataBatcher;
+use crate::ml_model::model::{Model, Predictor};
+use crate::ml_model::normalization::{Normalization, Normalizer};
 use crate::ml_model::training::TrainingConfig;
-use crate::price_data::CompaniesPriceData;
+use crate::ml_model::ExperimentConfig;
+use crate::price_data::{CompaniesPriceData, TradingCalendar};
 use burn::prelude::*;
 use burn::record::Recorder;
 
-use super::HOLD_LENGTH;
-
 pub fn infer<B: Backend>(
     artifact_dir: &str,
     companies_price_data: CompaniesPriceData,
     start_date: NaiveDate,
     end_date: NaiveDate,
+    calendar: &TradingCalendar,
+    experiment: &ExperimentConfig,
     device: B::Device,
 ) {
+    let (model, normalizer) = load_model::<B>(artifact_dir, experiment, &device);
+
+    let batcher = PriceDataBatcher::<B>::new(device.clone(), experiment.target);
+    let strategy = Strategy::new(&companies_price_data, calendar, experiment, normalizer);
+    let engine = backtest(&model, &batcher, strategy.clone(), start_date, end_date);
+
+    let report = PerformanceReport::new(&engine.value_history, calendar);
+    println!("{report}");
+
+    // the baselines trade the same universe with the same strategy, only the predictions differ.
+    let baselines = &experiment.baselines;
+    if !baselines.is_empty() {
+        let mut reports = vec![("model".to_string(), report)];
+        for baseline in baselines.iter() {
+            let engine = backtest(baseline, &batcher, strategy.clone(), start_date, end_date);
+            reports.push((
+                baseline.to_string(),
+                PerformanceReport::new(&engine.value_history, calendar),
+            ));
+        }
+        print_comparison(&reports);
+    }
+
+    plot_model_output(engine.value_history.clone());
+    plot_company_bias(&companies_price_data, start_date, end_date, &engine);
+}
+
+/// Backtests the model saved in `artifact_dir` from `start_date` until `end_date`, returning the value history.
+/// Used by the walk-forward evaluation, which backtests every fold on its own model.
+pub fn backtest_slice<B: Backend>(
+    artifact_dir: &str,
+    companies_price_data: &CompaniesPriceData,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    calendar: &TradingCalendar,
+    experiment: &ExperimentConfig,
+    device: B::Device,
+) -> Vec<(NaiveDate, f64)> {
+    let (model, normalizer) = load_model::<B>(artifact_dir, experiment, &device);
+
+    let batcher = PriceDataBatcher::<B>::new(device.clone(), experiment.target);
+    let strategy = Strategy::new(companies_price_data, calendar, experiment, normalizer);
+    let engine = backtest(&model, &batcher, strategy, start_date, end_date);
+
+    engine.value_history
+}
+
+/// Prints and plots the equity curve stitched from the folds of the walk-forward evaluation.
+pub fn report_walk_forward(value_history: Vec<(NaiveDate, f64)>, calendar: &TradingCalendar) {
+    let report = PerformanceReport::new(&value_history, calendar);
+    println!("{report}");
+
+    plot_model_output(value_history);
+}
+
+fn load_model<B: Backend>(
+    artifact_dir: &str,
+    experiment: &ExperimentConfig,
+    device: &B::Device,
+) -> (Model<B>, Normalizer) {
     let model_config = TrainingConfig::load(format!("{artifact_dir}/config.json"))
         .expect("training config could not be loaded");
 
     let record = CompactRecorder::new()
-        .load(format!("{artifact_dir}/model").into(), &device)
+        .load(format!("{artifact_dir}/model").into(), device)
         .expect("trained model could not be loaded");
 
-    let model = model_config.model.init::<B>(&device).load_record(record);
+    let model = model_config.model.init::<B>(device).load_record(record);
 
-    let batcher = PriceDataBatcher::<B>::new(device.clone());
-    let strategy = Strategy::new(&companies_price_data, HOLD_LENGTH, 10000.0);
+    // windows are scaled with the statistics fit while training, only the global scheme has any. Models
+    // trained before the normalizer was saved scale their windows on their own.
+    let path = format!("{artifact_dir}/normalizer.json");
+    let normalizer = match Path::new(&path).exists() {
+        false if experiment.normalization != Normalization::Global => {
+            Normalizer::new(experiment.normalization, experiment.features.clone())
+        }
+        _ => Normalizer::load(&path).expect("normalizer could not be loaded"),
+    };
+
+    (model, normalizer)
+}
+
+fn backtest<'a, B: Backend, M: Predictor<B>>(
+    model: &M,
+    batcher: &PriceDataBatcher<B>,
+    strategy: Strategy<'a>,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> StrategyEngine<'a> {
     let mut engine = StrategyEngine::new(start_date, end_date, strategy);
 
     while engine.date < engine.end_date {
-        engine.step_day(&batcher, &model);
+        engine.step_day(batcher, model);
     }
 
-    plot_model_output(engine.value_history.clone());
-    plot_company_bias(&companies_price_data, start_date, end_date, &engine);
+    engine
 }
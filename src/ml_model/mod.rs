@@ -1,32 +1,51 @@
+mod baseline;
+mod cross_validation;
 mod data;
 mod data_loader;
+mod experiment;
+mod features;
 mod inference;
 mod model;
+mod normalization;
+mod target;
 mod training;
+mod walk_forward;
 
+use crate::price_data::CalendarKind;
 use crate::price_data::CompaniesPriceData;
 use crate::price_data::CompanyPriceData;
+use crate::price_data::TradingCalendar;
 use burn::backend::autodiff::Autodiff;
 use burn::backend::libtorch::{LibTorch, LibTorchDevice};
+use burn::data::dataset::Dataset;
 use burn::optim::AdamConfig;
 use burn::tensor::{backend::Backend, ElementConversion, Tensor};
 use chrono::NaiveDate;
 use data::PriceDataBatch;
+use data_loader::TrainPriceDataSetConfig;
+pub use experiment::ExperimentConfig;
+pub use features::Feature;
+pub use model::Architecture;
+pub use target::TargetMode;
 use training::TrainingConfig;
 
 type MyBackend = LibTorch;
 type MyAudodiffBackend = Autodiff<MyBackend>;
 
-pub const HOLD_LENGTH: usize = 1;
-pub const CHUNK_SIZE: usize = 32;
-
 pub struct StockPredictor {
     pub artifact_dir: String,
+    pub experiment: ExperimentConfig,
+    // The calendar the training windows are built on, from the companies they are built from.
+    pub calendar: CalendarKind,
 }
 
 impl StockPredictor {
-    pub fn new(artifact_dir: String) -> Self {
-        StockPredictor { artifact_dir }
+    pub fn new(artifact_dir: String, experiment: ExperimentConfig, calendar: CalendarKind) -> Self {
+        StockPredictor {
+            artifact_dir,
+            experiment,
+            calendar,
+        }
     }
 
     pub fn train_model(&self, companies: CompaniesPriceData, device: LibTorchDevice) {
@@ -35,19 +54,16 @@ impl StockPredictor {
             companies.companies.len()
         );
 
-        let model_config = model::ModelConfig::new(32, 64);
-        let optimizer = AdamConfig::new();
-        let learning_rate = 0.0001;
-
-        let training_config =
-            TrainingConfig::new(model_config, optimizer, learning_rate, HOLD_LENGTH)
-                .with_num_epochs(10);
-
+        let training_config = training_config(&self.experiment);
+        let datasets = TrainPriceDataSetConfig::new(&self.experiment, self.calendar)
+            .init(companies, self.experiment.prediction_interval);
         training::train::<MyAudodiffBackend>(
             &self.artifact_dir,
             training_config,
-            companies,
+            &self.experiment,
+            datasets,
             device,
+            None,
         );
     }
 
@@ -56,6 +72,7 @@ impl StockPredictor {
         companies: CompaniesPriceData,
         start_date: NaiveDate,
         end_date: NaiveDate,
+        calendar: &TradingCalendar,
         device: LibTorchDevice,
     ) {
         println!(
@@ -63,29 +80,194 @@ impl StockPredictor {
             companies.companies.len()
         );
 
-        inference::infer::<MyBackend>(&self.artifact_dir, companies, start_date, end_date, device);
+        inference::infer::<MyBackend>(
+            &self.artifact_dir,
+            companies,
+            start_date,
+            end_date,
+            calendar,
+            &self.experiment,
+            device,
+        );
+    }
+
+    /// Cross-validates the experiment and every variant with the same purged k-fold split of the training universe
+    /// and prints the validation loss of every fold.
+    pub fn cross_validate(&self, companies: CompaniesPriceData, device: LibTorchDevice) {
+        let config = &self.experiment.cross_validation;
+        let experiments = config
+            .experiments(&self.experiment)
+            .expect("invalid cross-validation variants");
+
+        let cv_dir = format!("{}/cv", self.artifact_dir);
+        std::fs::remove_dir_all(&cv_dir).ok();
+
+        let mut results = Vec::new();
+        for (idx, (name, experiment)) in experiments.iter().enumerate() {
+            let mut losses = Vec::new();
+            for fold in 0..config.folds {
+                println!("Cross-validating {name}, fold {fold} of {}", config.folds);
+
+                let training_config = training_config(experiment);
+                let datasets = TrainPriceDataSetConfig::new(experiment, self.calendar).init_fold(
+                    companies.clone(),
+                    experiment.prediction_interval,
+                    config.folds,
+                    fold,
+                );
+                if datasets.0.is_empty() || datasets.1.is_empty() {
+                    println!("Fold {fold} has no training or validation windows, skipped");
+                    losses.push(f64::NAN);
+                    continue;
+                }
+
+                losses.push(training::train::<MyAudodiffBackend>(
+                    &format!("{cv_dir}/experiment_{idx}/fold_{fold}"),
+                    training_config,
+                    experiment,
+                    datasets,
+                    device.clone(),
+                    None,
+                ));
+            }
+            results.push((name.clone(), losses));
+        }
+
+        cross_validation::print_results(&results);
     }
+
+    /// Walks forward through the validation range in folds of `walk_forward.step_days` trading days. Every fold
+    /// trains a model on the training universe up to the fold and backtests the fold with it, the backtests are
+    /// stitched into a single equity curve.
+    pub fn walk_forward(
+        &self,
+        train_companies: &CompaniesPriceData,
+        validate_companies: &CompaniesPriceData,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        calendar: &TradingCalendar,
+        device: LibTorchDevice,
+    ) {
+        let experiment = &self.experiment;
+        let config = &experiment.walk_forward;
+
+        let days: Vec<NaiveDate> = calendar
+            .trading_days(start_date, end_date)
+            .into_iter()
+            .collect();
+        let folds: Vec<&[NaiveDate]> = days.chunks(config.step_days).collect();
+        println!("Walking forward through {} folds", folds.len());
+
+        let walk_dir = format!("{}/walk_forward", self.artifact_dir);
+        std::fs::remove_dir_all(&walk_dir).ok();
+
+        let mut histories = Vec::new();
+        for (idx, fold) in folds.iter().enumerate() {
+            let fold_start = fold[0];
+            // a fold ends on the first day of the next fold, the last fold at the end of the range.
+            let fold_end = folds.get(idx + 1).map(|next| next[0]).unwrap_or(end_date);
+            let fold_dir = format!("{walk_dir}/fold_{idx}");
+            println!("Fold {idx}: {fold_start} to {fold_end}");
+
+            let mut training_config = training_config(experiment);
+            let start_from = match config.fine_tune && idx > 0 {
+                true => {
+                    training_config = training_config.with_num_epochs(config.fine_tune_epochs);
+                    Some(format!("{walk_dir}/fold_{}/model", idx - 1))
+                }
+                false => None,
+            };
+
+            // every fold validates on the days just before it, the split date of the experiment is not used.
+            let training_data = config.training_data(train_companies, fold_start);
+            let split_date = config.split_date(
+                &training_data,
+                experiment.window_size,
+                experiment.prediction_interval,
+            );
+            let datasets = TrainPriceDataSetConfig::new(experiment, self.calendar)
+                .with_split_date(split_date)
+                .init(training_data, experiment.prediction_interval);
+            training::train::<MyAudodiffBackend>(
+                &fold_dir,
+                training_config,
+                experiment,
+                datasets,
+                device.clone(),
+                start_from.as_deref(),
+            );
+
+            histories.push(inference::backtest_slice::<MyBackend>(
+                &fold_dir,
+                validate_companies,
+                fold_start,
+                fold_end,
+                calendar,
+                experiment,
+                device.clone(),
+            ));
+        }
+
+        let value_history = walk_forward::stitch(histories, experiment.start_balance);
+        inference::report_walk_forward(value_history, calendar);
+    }
+}
+
+fn training_config(experiment: &ExperimentConfig) -> TrainingConfig {
+    let model_config = model::ModelConfig::new(
+        experiment.window_size,
+        experiment.features.len(),
+        experiment.hidden_size,
+    )
+    .with_architecture(experiment.architecture)
+    .with_sequence(experiment.sequence)
+    .with_num_layers(experiment.num_layers)
+    .with_dropout(experiment.dropout)
+    .with_num_heads(experiment.num_heads)
+    .with_kernel_size(experiment.kernel_size);
+    let optimizer = AdamConfig::new();
+
+    TrainingConfig::new(
+        model_config,
+        optimizer,
+        experiment.learning_rate,
+        experiment.prediction_interval,
+    )
+    .with_num_epochs(experiment.num_epochs)
+    .with_batch_size(experiment.batch_size)
+    .with_num_workers(experiment.num_workers)
+    .with_seed(experiment.seed)
+    .with_split_val(experiment.split_val)
 }
 
 #[derive(Debug, Clone)]
 pub struct NormCompanyPriceDataBatch<B: Backend> {
     pub company: CompanyPriceData,
-    pub data: Tensor<B, 2>,
-    pub targets: Tensor<B, 1>,
+    pub batch: PriceDataBatch<B>,
+    // The model output for the batch, set once the batch has been run through the model.
+    pub prediction: Option<Tensor<B, 2>>,
 }
 
 impl<B: Backend> NormCompanyPriceDataBatch<B> {
     pub fn new(company: CompanyPriceData, batch: PriceDataBatch<B>) -> Self {
         NormCompanyPriceDataBatch {
             company,
-            data: batch.data,
-            targets: batch.targets,
+            batch,
+            prediction: None,
         }
     }
 
-    pub fn norm_delta(&self) -> f64 {
-        let prediction: f64 = self.data.clone().flatten::<1>(0, 1).into_scalar().elem();
-        let actual: f64 = self.targets.clone().into_scalar().elem();
-        prediction - actual
+    /// The return the prediction expects over the horizon, whatever the target mode.
+    pub fn expected_return(&self) -> f64 {
+        let prediction: f64 = self
+            .prediction
+            .clone()
+            .expect("the batch has not been run through the model")
+            .flatten::<1>(0, 1)
+            .into_scalar()
+            .elem();
+        self.batch
+            .target_mode
+            .expected_return(prediction, &self.batch.contexts[0])
     }
 }
@@ -0,0 +1,46 @@
+use burn::{
+    module::Module,
+    nn::{
+        gru::{Gru, GruConfig},
+        Dropout, DropoutConfig, Linear, LinearConfig,
+    },
+    tensor::{backend::Backend, Tensor},
+};
+
+use super::{last_step, ModelConfig, PriceModel};
+
+#[derive(Module, Debug)]
+pub struct GruModel<B: Backend> {
+    input_layer: Linear<B>,
+    gru: Vec<Gru<B>>,
+    output_layer: Linear<B>,
+    dropout: Dropout,
+}
+
+impl<B: Backend> GruModel<B> {
+    pub fn new(config: &ModelConfig, device: &B::Device) -> Self {
+        let gru = (0..config.num_layers.max(1))
+            .map(|_| GruConfig::new(config.hidden_size, config.hidden_size, true).init(device))
+            .collect();
+
+        GruModel {
+            input_layer: LinearConfig::new(config.n_features, config.hidden_size).init(device),
+            gru,
+            output_layer: LinearConfig::new(config.hidden_size, 1).init(device),
+            dropout: DropoutConfig::new(config.dropout).init(),
+        }
+    }
+}
+
+impl<B: Backend> PriceModel<B> for GruModel<B> {
+    fn forward(&self, price_data: Tensor<B, 3>) -> Tensor<B, 2> {
+        let mut x = self.input_layer.forward(price_data);
+        for (idx, gru) in self.gru.iter().enumerate() {
+            if idx > 0 {
+                x = self.dropout.forward(x);
+            }
+            x = gru.forward(x, None);
+        }
+        self.output_layer.forward(last_step(x))
+    }
+}
@@ -0,0 +1,77 @@
+use burn::{
+    module::Module,
+    nn::{Dropout, DropoutConfig, Linear, LinearConfig, Lstm, LstmConfig},
+    tensor::{backend::Backend, Tensor},
+};
+
+use super::{last_step, ModelConfig, PriceModel};
+
+#[derive(Module, Debug)]
+pub struct LstmModel<B: Backend> {
+    input_layer: Linear<B>,
+    output_layer: Linear<B>,
+    lstm: Vec<Lstm<B>>,
+    dropout: Dropout,
+    sequence: bool,
+}
+
+impl<B: Backend> LstmModel<B> {
+    pub fn new(config: &ModelConfig, device: &B::Device) -> Self {
+        // a sequence model reads one day at a time, otherwise the whole window is a single input.
+        let input_size = match config.sequence {
+            true => config.n_features,
+            false => config.window_size * config.n_features,
+        };
+        let input_layer = LinearConfig::new(input_size, config.hidden_size)
+            .with_bias(true)
+            .init(device);
+        let output_layer = LinearConfig::new(config.hidden_size, 1)
+            .with_bias(true)
+            .init(device);
+        let lstm = (0..config.num_layers.max(1))
+            .map(|_| LstmConfig::new(config.hidden_size, config.hidden_size, true).init(device))
+            .collect();
+
+        LstmModel {
+            input_layer,
+            output_layer,
+            lstm,
+            dropout: DropoutConfig::new(config.dropout).init(),
+            sequence: config.sequence,
+        }
+    }
+
+    // Runs the stacked LSTM over the days of the window, [batch, seq_len, n_features], and predicts from the
+    // hidden state after the last day.
+    fn forward_sequence(&self, price_data: Tensor<B, 3>) -> Tensor<B, 2> {
+        let mut x = self.input_layer.forward(price_data);
+        for (idx, lstm) in self.lstm.iter().enumerate() {
+            if idx > 0 {
+                x = self.dropout.forward(x);
+            }
+            (_, x) = lstm.forward(x, None);
+        }
+        self.output_layer.forward(last_step(x))
+    }
+}
+
+impl<B: Backend> PriceModel<B> for LstmModel<B> {
+    fn forward(&self, price_data: Tensor<B, 3>) -> Tensor<B, 2> {
+        if self.sequence {
+            return self.forward_sequence(price_data);
+        }
+
+        // the whole window is read as a single step, [batch, 1, seq_len * n_features].
+        let [batch_size, seq_len, n_features] = price_data.dims();
+        let x = price_data.reshape([batch_size, 1, seq_len * n_features]);
+        let mut x = self.input_layer.forward(x);
+        for (idx, lstm) in self.lstm.iter().enumerate() {
+            if idx > 0 {
+                x = self.dropout.forward(x);
+            }
+            // burn returns the cell states first, the next layer and the output read the hidden states.
+            (_, x) = lstm.forward(x, None);
+        }
+        self.output_layer.forward(x).squeeze(2)
+    }
+}
@@ -0,0 +1,47 @@
+use burn::{
+    module::Module,
+    nn::{Dropout, DropoutConfig, Linear, LinearConfig, Relu},
+    tensor::{backend::Backend, Tensor},
+};
+
+use super::{ModelConfig, PriceModel};
+
+#[derive(Module, Debug)]
+pub struct MlpModel<B: Backend> {
+    hidden_layers: Vec<Linear<B>>,
+    output_layer: Linear<B>,
+    activation: Relu,
+    dropout: Dropout,
+}
+
+impl<B: Backend> MlpModel<B> {
+    pub fn new(config: &ModelConfig, device: &B::Device) -> Self {
+        let mut input_size = config.window_size * config.n_features;
+        let hidden_layers = (0..config.num_layers.max(1))
+            .map(|_| {
+                let layer = LinearConfig::new(input_size, config.hidden_size).init(device);
+                input_size = config.hidden_size;
+                layer
+            })
+            .collect();
+
+        MlpModel {
+            hidden_layers,
+            output_layer: LinearConfig::new(config.hidden_size, 1).init(device),
+            activation: Relu::new(),
+            dropout: DropoutConfig::new(config.dropout).init(),
+        }
+    }
+}
+
+impl<B: Backend> PriceModel<B> for MlpModel<B> {
+    fn forward(&self, price_data: Tensor<B, 3>) -> Tensor<B, 2> {
+        let mut x = price_data.flatten::<2>(1, 2);
+        for layer in self.hidden_layers.iter() {
+            x = self
+                .dropout
+                .forward(self.activation.forward(layer.forward(x)));
+        }
+        self.output_layer.forward(x)
+    }
+}
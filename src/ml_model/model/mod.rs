@@ -0,0 +1,118 @@
+mod gru;
+mod lstm;
+mod mlp;
+mod tcn;
+mod transformer;
+
+use burn::{
+    config::Config,
+    module::Module,
+    tensor::{backend::Backend, Tensor},
+};
+use serde::{Deserialize, Serialize};
+
+use super::data::PriceDataBatch;
+
+use gru::GruModel;
+use lstm::LstmModel;
+use mlp::MlpModel;
+use tcn::TcnModel;
+use transformer::TransformerModel;
+
+/// Implemented by every architecture, so that training and the backtest do not depend on a specific model.
+pub trait PriceModel<B: Backend> {
+    /// Predicts one value for every window of the batch, `[batch, seq_len, n_features]` to `[batch, 1]`.
+    fn forward(&self, price_data: Tensor<B, 3>) -> Tensor<B, 2>;
+}
+
+/// Anything that predicts the targets of a batch, the trained models as well as the baselines.
+pub trait Predictor<B: Backend> {
+    /// Predicts one target for every window of the batch, `[batch, 1]`.
+    fn predict(&self, batch: &PriceDataBatch<B>) -> Tensor<B, 2>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Architecture {
+    /// Fully connected layers over the flattened window.
+    Mlp,
+    /// Stacked LSTM layers, reading the window as a single step unless `sequence` is set.
+    Lstm,
+    /// Stacked GRU layers reading the window one day at a time.
+    Gru,
+    /// Causal dilated 1D convolutions with residual connections.
+    Tcn,
+    /// A transformer encoder with sinusoidal positional encoding.
+    Transformer,
+}
+
+#[derive(Config, Debug)]
+pub struct ModelConfig {
+    window_size: usize,
+    n_features: usize,
+    hidden_size: usize,
+    #[config(default = "Architecture::Lstm")]
+    architecture: Architecture,
+    #[doc = "Feed the window to the LSTM day by day instead of as a single step"]
+    #[config(default = false)]
+    sequence: bool,
+    #[doc = "The number of hidden layers, recurrent layers, convolution blocks or encoder layers"]
+    #[config(default = 1)]
+    num_layers: usize,
+    #[doc = "Dropout applied between the layers while training"]
+    #[config(default = 0.0)]
+    dropout: f64,
+    #[doc = "Attention heads of the transformer, must divide the hidden size"]
+    #[config(default = 4)]
+    num_heads: usize,
+    #[doc = "Kernel size of the TCN convolutions"]
+    #[config(default = 3)]
+    kernel_size: usize,
+}
+
+impl ModelConfig {
+    pub fn init<B: Backend>(&self, device: &B::Device) -> Model<B> {
+        match self.architecture {
+            Architecture::Mlp => Model::Mlp(MlpModel::new(self, device)),
+            Architecture::Lstm => Model::Lstm(LstmModel::new(self, device)),
+            Architecture::Gru => Model::Gru(GruModel::new(self, device)),
+            Architecture::Tcn => Model::Tcn(TcnModel::new(self, device)),
+            Architecture::Transformer => Model::Transformer(TransformerModel::new(self, device)),
+        }
+    }
+}
+
+#[derive(Module, Debug)]
+pub enum Model<B: Backend> {
+    Mlp(MlpModel<B>),
+    Lstm(LstmModel<B>),
+    Gru(GruModel<B>),
+    Tcn(TcnModel<B>),
+    Transformer(TransformerModel<B>),
+}
+
+impl<B: Backend> PriceModel<B> for Model<B> {
+    fn forward(&self, price_data: Tensor<B, 3>) -> Tensor<B, 2> {
+        let x = price_data.detach();
+        match self {
+            Model::Mlp(model) => model.forward(x),
+            Model::Lstm(model) => model.forward(x),
+            Model::Gru(model) => model.forward(x),
+            Model::Tcn(model) => model.forward(x),
+            Model::Transformer(model) => model.forward(x),
+        }
+    }
+}
+
+impl<B: Backend> Predictor<B> for Model<B> {
+    fn predict(&self, batch: &PriceDataBatch<B>) -> Tensor<B, 2> {
+        self.forward(batch.data.clone())
+    }
+}
+
+// Keeps only the last day of a `[batch, seq_len, hidden]` sequence, `[batch, hidden]`.
+fn last_step<B: Backend>(x: Tensor<B, 3>) -> Tensor<B, 2> {
+    let [batch_size, seq_len, hidden_size] = x.dims();
+    x.slice([0..batch_size, seq_len - 1..seq_len, 0..hidden_size])
+        .squeeze(1)
+}
@@ -0,0 +1,63 @@
+use burn::{
+    module::Module,
+    nn::{
+        conv::{Conv1d, Conv1dConfig},
+        Dropout, DropoutConfig, Linear, LinearConfig, PaddingConfig1d, Relu,
+    },
+    tensor::{backend::Backend, Tensor},
+};
+
+use super::{last_step, ModelConfig, PriceModel};
+
+/// A temporal convolutional network, every block doubles the dilation so the receptive field grows exponentially.
+#[derive(Module, Debug)]
+pub struct TcnModel<B: Backend> {
+    input_layer: Linear<B>,
+    blocks: Vec<Conv1d<B>>,
+    output_layer: Linear<B>,
+    activation: Relu,
+    dropout: Dropout,
+}
+
+impl<B: Backend> TcnModel<B> {
+    pub fn new(config: &ModelConfig, device: &B::Device) -> Self {
+        let blocks = (0..config.num_layers.max(1))
+            .map(|idx| {
+                let dilation = 1 << idx;
+                Conv1dConfig::new(config.hidden_size, config.hidden_size, config.kernel_size)
+                    .with_dilation(dilation)
+                    .with_padding(PaddingConfig1d::Explicit(
+                        (config.kernel_size - 1) * dilation,
+                    ))
+                    .init(device)
+            })
+            .collect();
+
+        TcnModel {
+            input_layer: LinearConfig::new(config.n_features, config.hidden_size).init(device),
+            blocks,
+            output_layer: LinearConfig::new(config.hidden_size, 1).init(device),
+            activation: Relu::new(),
+            dropout: DropoutConfig::new(config.dropout).init(),
+        }
+    }
+}
+
+impl<B: Backend> PriceModel<B> for TcnModel<B> {
+    fn forward(&self, price_data: Tensor<B, 3>) -> Tensor<B, 2> {
+        // convolutions run over the last dimension, [batch, hidden, seq_len].
+        let mut x = self.input_layer.forward(price_data).swap_dims(1, 2);
+        let [batch_size, hidden_size, seq_len] = x.dims();
+
+        for block in self.blocks.iter() {
+            // the padding is applied to both ends, keeping the first `seq_len` outputs makes every output
+            // depend only on its own day and the days before it.
+            let y = block
+                .forward(x.clone())
+                .slice([0..batch_size, 0..hidden_size, 0..seq_len]);
+            x = x + self.dropout.forward(self.activation.forward(y));
+        }
+
+        self.output_layer.forward(last_step(x.swap_dims(1, 2)))
+    }
+}
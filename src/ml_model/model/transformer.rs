@@ -0,0 +1,49 @@
+use burn::{
+    module::Module,
+    nn::{
+        transformer::{TransformerEncoder, TransformerEncoderConfig, TransformerEncoderInput},
+        Linear, LinearConfig, PositionalEncoding, PositionalEncodingConfig,
+    },
+    tensor::{backend::Backend, Tensor},
+};
+
+use super::{last_step, ModelConfig, PriceModel};
+
+#[derive(Module, Debug)]
+pub struct TransformerModel<B: Backend> {
+    input_layer: Linear<B>,
+    positional_encoding: PositionalEncoding<B>,
+    encoder: TransformerEncoder<B>,
+    output_layer: Linear<B>,
+}
+
+impl<B: Backend> TransformerModel<B> {
+    pub fn new(config: &ModelConfig, device: &B::Device) -> Self {
+        let encoder = TransformerEncoderConfig::new(
+            config.hidden_size,
+            config.hidden_size * 4,
+            config.num_heads,
+            config.num_layers.max(1),
+        )
+        .with_dropout(config.dropout)
+        .init(device);
+
+        TransformerModel {
+            input_layer: LinearConfig::new(config.n_features, config.hidden_size).init(device),
+            positional_encoding: PositionalEncodingConfig::new(config.hidden_size)
+                .with_max_sequence_size(config.window_size.max(1))
+                .init(device),
+            encoder,
+            output_layer: LinearConfig::new(config.hidden_size, 1).init(device),
+        }
+    }
+}
+
+impl<B: Backend> PriceModel<B> for TransformerModel<B> {
+    fn forward(&self, price_data: Tensor<B, 3>) -> Tensor<B, 2> {
+        let x = self.input_layer.forward(price_data);
+        let x = self.positional_encoding.forward(x);
+        let x = self.encoder.forward(TransformerEncoderInput::new(x));
+        self.output_layer.forward(last_step(x))
+    }
+}
@@ -0,0 +1,278 @@
+use std::fs::File;
+
+use serde::{Deserialize, Serialize};
+
+use super::{data_loader::BurnPriceDataItem, features::Feature};
+
+/// How the features of a window are scaled before the model sees them.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Normalization {
+    /// Every feature scaled to its range in the window.
+    #[default]
+    MinMax,
+    /// Every feature scaled to zero mean and unit standard deviation in the window.
+    ZScore,
+    /// Prices as the change from the last close of the window, 0.02 for 2% above it. Features that are not
+    /// prices, such as the volume or the RSI, are scaled as a z-score in the window.
+    RelativeToLast,
+    /// Every feature scaled by its mean and standard deviation over all training windows. The statistics are fit
+    /// once and stay fixed for every window read after training.
+    Global,
+}
+
+// The mean and standard deviation of a feature, or any other center and spread to scale it by.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct FeatureStats {
+    mean: f64,
+    std: f64,
+}
+
+impl FeatureStats {
+    fn new(values: impl Iterator<Item = f64> + Clone) -> FeatureStats {
+        let (sum, count) = values
+            .clone()
+            .fold((0.0, 0usize), |(sum, count), x| (sum + x, count + 1));
+        let mean = sum / count.max(1) as f64;
+        let variance = values.map(|x| (x - mean).powi(2)).sum::<f64>() / count.max(1) as f64;
+
+        FeatureStats {
+            mean,
+            std: variance.sqrt(),
+        }
+    }
+
+    // A feature that does not move is all zeros.
+    fn scale(&self, x: f64) -> f64 {
+        if self.std > 0.0 {
+            (x - self.mean) / self.std
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Scales windows with the configured scheme. The statistics of the global scheme are fit on the training
+/// windows and saved next to the trained model, so that the backtest scales its windows the same way.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Normalizer {
+    pub scheme: Normalization,
+    features: Vec<Feature>,
+    // One entry per feature, only fit for the global scheme.
+    stats: Vec<FeatureStats>,
+}
+
+impl Normalizer {
+    pub fn new(scheme: Normalization, features: Vec<Feature>) -> Normalizer {
+        Normalizer {
+            scheme,
+            features,
+            stats: Vec::new(),
+        }
+    }
+
+    /// Fits the statistics of the global scheme on the finite values of the training windows.
+    pub fn fit(&mut self, items: impl Iterator<Item = BurnPriceDataItem>) {
+        if self.scheme != Normalization::Global {
+            return;
+        }
+
+        // the windows are read once, so the moments of every feature are accumulated together. Welford's update
+        // keeps the variance accurate for features whose mean is large next to their spread, such as prices.
+        let n_features = self.features.len();
+        let mut counts = vec![0usize; n_features];
+        let mut means = vec![0.0; n_features];
+        let mut squares = vec![0.0; n_features];
+        for item in items {
+            for (idx, x) in item.data.iter().enumerate() {
+                if x.is_finite() {
+                    let feature = idx % n_features;
+                    counts[feature] += 1;
+                    let delta = x - means[feature];
+                    means[feature] += delta / counts[feature] as f64;
+                    squares[feature] += delta * (x - means[feature]);
+                }
+            }
+        }
+
+        self.stats = (0..n_features)
+            .map(|feature| FeatureStats {
+                mean: means[feature],
+                std: (squares[feature] / counts[feature].max(1) as f64).sqrt(),
+            })
+            .collect();
+    }
+
+    /// Scales a window given row by row, `closes` are the raw closes of its days.
+    pub fn normalize(&self, data: &[f64], closes: &[f64]) -> Vec<f64> {
+        let n_features = self.features.len();
+        let last_close = closes.last().cloned().unwrap_or(f64::NAN);
+
+        let mut new_data = data.to_vec();
+        for (idx, feature) in self.features.iter().enumerate() {
+            let column = data.iter().skip(idx).step_by(n_features).cloned();
+
+            // every scheme subtracts a center and divides by a spread.
+            let stats = match self.scheme {
+                Normalization::MinMax => {
+                    let min = column.clone().fold(f64::INFINITY, f64::min);
+                    let max = column.fold(f64::NEG_INFINITY, f64::max);
+                    FeatureStats {
+                        mean: min,
+                        std: max - min,
+                    }
+                }
+                Normalization::RelativeToLast if feature.is_price() => FeatureStats {
+                    mean: last_close,
+                    std: last_close,
+                },
+                Normalization::ZScore | Normalization::RelativeToLast => FeatureStats::new(column),
+                Normalization::Global => self
+                    .stats
+                    .get(idx)
+                    .cloned()
+                    .expect("the normalizer has not been fit"),
+            };
+
+            for x in new_data.iter_mut().skip(idx).step_by(n_features) {
+                *x = stats.scale(*x);
+            }
+        }
+
+        return new_data;
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let file = File::create(path).map_err(|err| err.to_string())?;
+        serde_json::to_writer_pretty(file, self).map_err(|err| err.to_string())
+    }
+
+    pub fn load(path: &str) -> Result<Normalizer, String> {
+        let file = File::open(path).map_err(|err| err.to_string())?;
+        serde_json::from_reader(file).map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two features, the close and the volume, over three days.
+    const DATA: [f64; 6] = [10.0, 100.0, 12.0, 300.0, 14.0, 200.0];
+    const CLOSES: [f64; 3] = [10.0, 12.0, 14.0];
+
+    fn normalizer(scheme: Normalization) -> Normalizer {
+        Normalizer::new(scheme, vec![Feature::Close, Feature::Volume])
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn min_max_scales_to_the_window_range() {
+        let data = normalizer(Normalization::MinMax).normalize(&DATA, &CLOSES);
+        assert_close(&data, &[0.0, 0.0, 0.5, 1.0, 1.0, 0.5]);
+    }
+
+    #[test]
+    fn z_score_scales_to_the_window_moments() {
+        let data = normalizer(Normalization::ZScore).normalize(&DATA, &CLOSES);
+        let z = 1.5f64.sqrt();
+        assert_close(&data, &[-z, -z, 0.0, z, z, 0.0]);
+    }
+
+    #[test]
+    fn relative_to_last_scales_prices_by_the_last_close() {
+        let data = normalizer(Normalization::RelativeToLast).normalize(&DATA, &CLOSES);
+        let z = 1.5f64.sqrt();
+        assert_close(&data, &[-4.0 / 14.0, -z, -2.0 / 14.0, z, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn flat_features_are_zeros() {
+        let data = [5.0, 1.0, 5.0, 1.0];
+        for scheme in [
+            Normalization::MinMax,
+            Normalization::ZScore,
+            Normalization::RelativeToLast,
+        ] {
+            let data = normalizer(scheme).normalize(&data, &[5.0, 5.0]);
+            assert_close(&data[1..2], &[0.0]);
+            assert_close(&data[3..4], &[0.0]);
+        }
+    }
+
+    #[test]
+    fn global_scales_by_the_training_windows() {
+        let items = [
+            BurnPriceDataItem::from_data_vec(
+                vec![1.0, 10.0, 3.0, f64::NAN],
+                2,
+                vec![1.0, 3.0],
+                0.0,
+            ),
+            BurnPriceDataItem::from_data_vec(vec![5.0, 30.0], 2, vec![5.0], 0.0),
+        ];
+        let mut normalizer = normalizer(Normalization::Global);
+        normalizer.fit(items.into_iter().map(Result::unwrap));
+
+        let data = normalizer.normalize(&[3.0, 20.0, 7.0, 40.0], &[3.0, 7.0]);
+        let std = (8.0f64 / 3.0).sqrt();
+        assert_close(&data, &[0.0, 0.0, 4.0 / std, 2.0]);
+    }
+
+    #[test]
+    fn global_fit_is_accurate_for_large_means() {
+        let data = vec![1e9, 0.0, 1e9 + 1.0, 0.0, 1e9 + 2.0, 0.0];
+        let mut normalizer = normalizer(Normalization::Global);
+        normalizer.fit(std::iter::once(
+            BurnPriceDataItem::from_data_vec(data, 2, CLOSES.to_vec(), 0.0).unwrap(),
+        ));
+
+        assert_eq!(normalizer.stats[0].mean, 1e9 + 1.0);
+        assert!((normalizer.stats[0].std - (2.0f64 / 3.0).sqrt()).abs() < 1e-9);
+        assert_eq!(normalizer.stats[1].std, 0.0);
+    }
+
+    #[test]
+    fn other_schemes_are_not_fit() {
+        let mut normalizer = normalizer(Normalization::ZScore);
+        normalizer.fit(std::iter::once(
+            BurnPriceDataItem::from_data_vec(DATA.to_vec(), 2, CLOSES.to_vec(), 0.0).unwrap(),
+        ));
+        assert!(normalizer.stats.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "the normalizer has not been fit")]
+    fn global_needs_to_be_fit() {
+        normalizer(Normalization::Global).normalize(&DATA, &CLOSES);
+    }
+
+    #[test]
+    fn saved_normalizer_scales_the_same() {
+        let mut fitted = normalizer(Normalization::Global);
+        fitted.fit(std::iter::once(
+            BurnPriceDataItem::from_data_vec(DATA.to_vec(), 2, CLOSES.to_vec(), 0.0).unwrap(),
+        ));
+
+        let path = std::env::temp_dir().join(format!("normalizer-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        fitted.save(path).unwrap();
+        let loaded = Normalizer::load(path);
+        std::fs::remove_file(path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.scheme, Normalization::Global);
+        assert_eq!(loaded.features, fitted.features);
+        assert_close(
+            &loaded.normalize(&DATA, &CLOSES),
+            &fitted.normalize(&DATA, &CLOSES),
+        );
+        assert!(Normalizer::load("missing/normalizer.json").is_err());
+    }
+}
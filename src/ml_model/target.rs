@@ -0,0 +1,223 @@
+use burn::{
+    nn::loss::{BinaryCrossEntropyLossConfig, MseLoss, Reduction},
+    tensor::{backend::Backend, Tensor},
+};
+use serde::{Deserialize, Serialize};
+
+/// What the model learns to predict about the close `horizon` days after the last day of the window.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetMode {
+    /// The close scaled by the low and high close of the window, or by the last close when the window is flat.
+    #[default]
+    NormalizedPrice,
+    /// The change from the last close, 0.02 for a 2% gain.
+    SimpleReturn,
+    /// The natural log of the close over the last close.
+    LogReturn,
+    /// The log return divided by the expected move over the horizon, the daily volatility of the window
+    /// times the square root of the horizon. Comparable across calm and volatile stocks.
+    VolScaledReturn,
+    /// 1 when the close is above the last close, 0 otherwise. The model outputs the logit of a rise.
+    Direction,
+}
+
+/// The facts about a window that are needed to encode a close as a target and to decode a prediction.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TargetContext {
+    pub last: f64,
+    pub min: f64,
+    pub max: f64,
+    // The standard deviation of the daily log returns in the window.
+    pub volatility: f64,
+    pub horizon: usize,
+}
+
+impl TargetContext {
+    pub fn new(closes: &[f64], horizon: usize) -> TargetContext {
+        let log_returns: Vec<f64> = closes
+            .windows(2)
+            .map(|pair| (pair[1] / pair[0]).ln())
+            .collect();
+        let mean = log_returns.iter().sum::<f64>() / log_returns.len().max(1) as f64;
+        let variance = log_returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>()
+            / log_returns.len().max(1) as f64;
+
+        TargetContext {
+            last: closes.last().cloned().unwrap_or(f64::NAN),
+            min: closes.iter().cloned().fold(f64::INFINITY, f64::min),
+            max: closes.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            volatility: variance.sqrt(),
+            horizon,
+        }
+    }
+
+    // A window whose closes do not move is scaled by its price instead.
+    fn range(&self) -> f64 {
+        if self.max > self.min {
+            self.max - self.min
+        } else {
+            self.last.abs()
+        }
+    }
+
+    fn expected_move(&self) -> f64 {
+        self.volatility * (self.horizon as f64).sqrt()
+    }
+}
+
+impl TargetMode {
+    /// The training target for the future close, NaN when the window cannot express it.
+    pub fn encode(&self, future: f64, context: &TargetContext) -> f64 {
+        match self {
+            TargetMode::NormalizedPrice => match context.range() {
+                range if range > 0.0 => (future - context.min) / range,
+                _ => f64::NAN,
+            },
+            TargetMode::SimpleReturn => future / context.last - 1.0,
+            TargetMode::LogReturn => (future / context.last).ln(),
+            TargetMode::VolScaledReturn => match context.expected_move() {
+                expected if expected > 0.0 => (future / context.last).ln() / expected,
+                _ => f64::NAN,
+            },
+            TargetMode::Direction => match future > context.last {
+                true => 1.0,
+                false => 0.0,
+            },
+        }
+    }
+
+    /// Encodes a predicted close the way the model output is read, used by predictors that forecast prices.
+    /// A direction is given as a logit, the vol-scaled return, so that larger moves are more certain. A window
+    /// that does not move, such as a halt that was forward filled, has no scale and gives an even logit.
+    pub fn encode_prediction(&self, future: f64, context: &TargetContext) -> f64 {
+        match self {
+            TargetMode::Direction => match TargetMode::VolScaledReturn.encode(future, context) {
+                logit if logit.is_finite() => logit,
+                _ => 0.0,
+            },
+            _ => self.encode(future, context),
+        }
+    }
+
+    /// Turns a model output back into the return it expects over the horizon, so that predictions of every
+    /// target mode are ranked the same way. A direction is ranked by the probability of a rise instead.
+    pub fn expected_return(&self, output: f64, context: &TargetContext) -> f64 {
+        match self {
+            TargetMode::NormalizedPrice => {
+                (context.min + output * context.range()) / context.last - 1.0
+            }
+            TargetMode::SimpleReturn => output,
+            TargetMode::LogReturn => output.exp() - 1.0,
+            TargetMode::VolScaledReturn => (output * context.expected_move()).exp() - 1.0,
+            TargetMode::Direction => 1.0 / (1.0 + (-output).exp()) - 0.5,
+        }
+    }
+
+    /// The mean loss of a batch, binary cross entropy on logits for a direction and the mean squared error otherwise.
+    pub fn loss<B: Backend>(&self, output: Tensor<B, 2>, targets: Tensor<B, 2>) -> Tensor<B, 1> {
+        match self {
+            TargetMode::Direction => BinaryCrossEntropyLossConfig::new()
+                .with_logits(true)
+                .init(&output.device())
+                .forward(output, targets.int()),
+            _ => MseLoss::new().forward(output, targets, Reduction::Mean),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODES: [TargetMode; 5] = [
+        TargetMode::NormalizedPrice,
+        TargetMode::SimpleReturn,
+        TargetMode::LogReturn,
+        TargetMode::VolScaledReturn,
+        TargetMode::Direction,
+    ];
+
+    fn context() -> TargetContext {
+        TargetContext::new(&[100.0, 102.0, 99.0, 104.0, 101.0], 4)
+    }
+
+    #[test]
+    fn context_from_closes() {
+        let context = context();
+        assert_eq!(context.last, 101.0);
+        assert_eq!(context.min, 99.0);
+        assert_eq!(context.max, 104.0);
+        assert_eq!(context.range(), 5.0);
+        assert!(context.volatility > 0.0);
+        assert!((context.expected_move() - context.volatility * 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn flat_window_is_scaled_by_its_price() {
+        let context = TargetContext::new(&[50.0, 50.0, 50.0], 1);
+        assert_eq!(context.range(), 50.0);
+        assert_eq!(context.volatility, 0.0);
+        assert_eq!(TargetMode::NormalizedPrice.encode(55.0, &context), 0.1);
+        assert!(TargetMode::VolScaledReturn.encode(55.0, &context).is_nan());
+
+        // a direction still accepts the window, so a predicted close gives a finite logit.
+        assert_eq!(TargetMode::Direction.encode(55.0, &context), 1.0);
+        assert_eq!(TargetMode::Direction.encode_prediction(55.0, &context), 0.0);
+        assert_eq!(
+            TargetMode::Direction.expected_return(
+                TargetMode::Direction.encode_prediction(55.0, &context),
+                &context
+            ),
+            0.0
+        );
+    }
+
+    #[test]
+    fn encoded_targets_decode_to_the_return() {
+        let context = context();
+        for future in [90.0, 101.0, 108.5] {
+            let expected = future / context.last - 1.0;
+            for mode in MODES.iter().filter(|mode| **mode != TargetMode::Direction) {
+                let decoded = mode.expected_return(mode.encode(future, &context), &context);
+                assert!(
+                    (decoded - expected).abs() < 1e-9,
+                    "{:?} decoded {} for {}",
+                    mode,
+                    decoded,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn predictions_rank_the_same_as_prices() {
+        let context = context();
+        let futures = [95.0, 100.0, 101.0, 103.0, 110.0];
+        for mode in MODES {
+            let ranked: Vec<f64> = futures
+                .iter()
+                .map(|future| {
+                    mode.expected_return(mode.encode_prediction(*future, &context), &context)
+                })
+                .collect();
+            assert!(
+                ranked.windows(2).all(|pair| pair[0] < pair[1]),
+                "{:?} ranked {:?}",
+                mode,
+                ranked
+            );
+        }
+    }
+
+    #[test]
+    fn direction_is_a_rise() {
+        let context = context();
+        assert_eq!(TargetMode::Direction.encode(101.5, &context), 1.0);
+        assert_eq!(TargetMode::Direction.encode(101.0, &context), 0.0);
+        assert_eq!(TargetMode::Direction.expected_return(0.0, &context), 0.0);
+        assert!(TargetMode::Direction.expected_return(2.0, &context) > 0.0);
+        assert!(TargetMode::Direction.expected_return(-2.0, &context) < 0.0);
+    }
+}
@@ -1,34 +1,32 @@
-use crate::price_data::CompaniesPriceData;
-
 use super::{
     data::{PriceDataBatch, PriceDataBatcher},
-    data_loader::TrainPriceDataSetConfig,
-    model::{Model, ModelConfig},
+    data_loader::{NormBurnPriceDataItem, SampledDataSet, TrainPriceDataSet},
+    experiment::ExperimentConfig,
+    model::{Model, ModelConfig, Predictor, PriceModel},
+    normalization::Normalizer,
 };
 use burn::{
     config::Config,
-    data::dataloader::DataLoaderBuilder,
-    module::Module,
-    nn::loss::{MseLoss, Reduction},
+    data::{
+        dataloader::{batcher::Batcher, DataLoaderBuilder},
+        dataset::Dataset,
+    },
+    module::{AutodiffModule, Module},
     optim::AdamConfig,
-    record::CompactRecorder,
+    record::{CompactRecorder, Recorder},
     tensor::backend::{AutodiffBackend, Backend},
-    tensor::Tensor,
+    tensor::ElementConversion,
     train::{
         metric::LossMetric, LearnerBuilder, RegressionOutput, TrainOutput, TrainStep, ValidStep,
     },
 };
 
 impl<B: Backend> Model<B> {
-    pub fn forward_regression(
-        &self,
-        price_data: Tensor<B, 2>,
-        targets: Tensor<B, 1>,
-    ) -> RegressionOutput<B> {
-        let targets = targets.unsqueeze();
-        let output = self.forward(price_data.clone());
+    pub fn forward_regression(&self, batch: PriceDataBatch<B>) -> RegressionOutput<B> {
+        let targets = batch.targets.unsqueeze_dim(1);
+        let output = self.forward(batch.data);
 
-        let loss = MseLoss::new().forward(output.clone(), targets.clone(), Reduction::Mean);
+        let loss = batch.target_mode.loss(output.clone(), targets.clone());
 
         RegressionOutput {
             loss,
@@ -40,14 +38,14 @@ impl<B: Backend> Model<B> {
 
 impl<B: AutodiffBackend> TrainStep<PriceDataBatch<B>, RegressionOutput<B>> for Model<B> {
     fn step(&self, batch: PriceDataBatch<B>) -> TrainOutput<RegressionOutput<B>> {
-        let item = self.forward_regression(batch.data, batch.targets);
+        let item = self.forward_regression(batch);
         TrainOutput::new(self, item.loss.backward(), item)
     }
 }
 
 impl<B: Backend> ValidStep<PriceDataBatch<B>, RegressionOutput<B>> for Model<B> {
     fn step(&self, batch: PriceDataBatch<B>) -> RegressionOutput<B> {
-        return self.forward_regression(batch.data, batch.targets);
+        return self.forward_regression(batch);
     }
 }
 
@@ -65,7 +63,7 @@ pub struct TrainingConfig {
     pub seed: u64,
     pub learning_rate: f64,
     #[config(default = 0.9)]
-    split_val: f32,
+    pub split_val: f32,
     #[doc = "Number of days to predict in the future"]
     prediction_interval: usize,
 }
@@ -75,29 +73,42 @@ fn create_artifact_dir(artifact_dir: &str) {
     std::fs::create_dir_all(artifact_dir).ok();
 }
 
+/// Trains a model on the training set and saves it to `artifact_dir`, returning its loss on the test set.
 pub fn train<B: AutodiffBackend>(
     artifact_dir: &str,
     config: TrainingConfig,
-    companies: CompaniesPriceData,
+    experiment: &ExperimentConfig,
+    datasets: (TrainPriceDataSet, TrainPriceDataSet, Normalizer),
     device: B::Device,
-) {
+    start_from: Option<&str>,
+) -> f64 {
     create_artifact_dir(artifact_dir);
     config
         .save(format!("{artifact_dir}/config.json"))
         .expect("Cannot save config");
+    experiment
+        .save(&format!("{artifact_dir}/experiment.json"))
+        .expect("Cannot save experiment config");
 
     B::seed(config.seed);
 
-    let batcher_train = PriceDataBatcher::<B>::new(device.clone());
-    let batcher_valid = PriceDataBatcher::<B::InnerBackend>::new(device.clone());
+    let batcher_train = PriceDataBatcher::<B>::new(device.clone(), experiment.target);
+    let batcher_valid = PriceDataBatcher::<B::InnerBackend>::new(device.clone(), experiment.target);
 
-    let (train, test) =
-        TrainPriceDataSetConfig::new(config.split_val).init(companies, config.prediction_interval);
+    let (train, test, normalizer) = datasets;
+    normalizer
+        .save(&format!("{artifact_dir}/normalizer.json"))
+        .expect("Cannot save normalizer");
 
+    // the validation windows are kept to measure the trained model and compare it against the baselines.
+    let valid_items = test.clone();
+
+    // the loader gives every worker a contiguous slice of the dataset and would only shuffle within it, so the
+    // sampled dataset shuffles the windows over the whole dataset instead.
     let dataloader_train = DataLoaderBuilder::new(batcher_train)
         .batch_size(config.batch_size)
-        .num_workers(4)
-        .build(train);
+        .num_workers(config.num_workers)
+        .build(SampledDataSet::new(train, experiment.sampling, config.seed));
 
     let dataloader_test = DataLoaderBuilder::new(batcher_valid)
         .batch_size(config.batch_size)
@@ -105,6 +116,18 @@ pub fn train<B: AutodiffBackend>(
         .num_workers(config.num_workers)
         .build(test);
 
+    // fine-tuning continues from the weights of a model saved before instead of a new model.
+    let model = config.model.init::<B>(&device);
+    let model = match start_from {
+        Some(path) => {
+            let record = CompactRecorder::new()
+                .load(path.into(), &device)
+                .expect("model to fine-tune could not be loaded");
+            model.load_record(record)
+        }
+        None => model,
+    };
+
     let learner = LearnerBuilder::new(artifact_dir)
         .metric_train_numeric(LossMetric::new())
         .metric_valid_numeric(LossMetric::new())
@@ -112,14 +135,49 @@ pub fn train<B: AutodiffBackend>(
         .devices(vec![device.clone()])
         .num_epochs(config.num_epochs)
         .summary()
-        .build(
-            config.model.init::<B>(&device),
-            config.optimizer.init(),
-            config.learning_rate,
-        );
+        .build(model, config.optimizer.init(), config.learning_rate);
 
     let model_trained = learner.fit(dataloader_train, dataloader_test);
+    let model_valid = model_trained.valid();
     model_trained
         .save_file(format!("{artifact_dir}/model"), &CompactRecorder::new())
         .expect("Trained model could not be saved");
+
+    let batcher = PriceDataBatcher::<B::InnerBackend>::new(device.clone(), experiment.target);
+    let loss = validation_loss(&model_valid, &batcher, &valid_items, config.batch_size);
+
+    let baselines = &experiment.baselines;
+    if !baselines.is_empty() {
+        println!("Validation loss on {} windows:", valid_items.len());
+        println!("  {:<24}{loss:.6}", "model");
+        for baseline in baselines.iter() {
+            let loss = validation_loss(baseline, &batcher, &valid_items, config.batch_size);
+            println!("  {:<24}{loss:.6}", baseline.to_string());
+        }
+    }
+
+    loss
+}
+
+/// The mean loss of the predictions over every item, for the target mode of the batcher.
+pub fn validation_loss<B: Backend, M: Predictor<B>>(
+    model: &M,
+    batcher: &PriceDataBatcher<B>,
+    items: &impl Dataset<NormBurnPriceDataItem>,
+    batch_size: usize,
+) -> f64 {
+    let mut total = 0.0;
+    let indices: Vec<usize> = (0..items.len()).collect();
+    for chunk in indices.chunks(batch_size.max(1)) {
+        let chunk: Vec<NormBurnPriceDataItem> =
+            chunk.iter().filter_map(|idx| items.get(*idx)).collect();
+        let count = chunk.len();
+        let batch = batcher.batch(chunk);
+        let output = model.predict(&batch);
+        let loss = batch
+            .target_mode
+            .loss(output, batch.targets.clone().unsqueeze_dim(1));
+        total += loss.into_scalar().elem::<f64>() * count as f64;
+    }
+    total / items.len().max(1) as f64
 }
@@ -0,0 +1,220 @@
+use std::collections::BTreeSet;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::price_data::{CompaniesPriceData, CompanyPriceData};
+
+/// Which days every fold of the walk-forward evaluation trains on.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WalkForwardWindow {
+    /// The last `train_days` trading days before the fold.
+    Rolling,
+    /// Every day from the start of the training range to the fold.
+    #[default]
+    Expanding,
+}
+
+/// Settings of the `walk-forward` command, which retrains the model before every slice of the validation range
+/// and backtests the slice on the model trained only on the days before it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WalkForwardConfig {
+    pub window: WalkForwardWindow,
+    // The trading days of history a rolling window trains on.
+    pub train_days: usize,
+    // The trading days backtested by every fold.
+    pub step_days: usize,
+    // Continues training the model of the previous fold instead of training every fold from scratch.
+    pub fine_tune: bool,
+    // The epochs of every fine-tuned fold, the first fold trains for `num_epochs`.
+    pub fine_tune_epochs: usize,
+}
+
+impl Default for WalkForwardConfig {
+    fn default() -> Self {
+        WalkForwardConfig {
+            window: WalkForwardWindow::Expanding,
+            train_days: 756,
+            step_days: 63,
+            fine_tune: false,
+            fine_tune_epochs: 2,
+        }
+    }
+}
+
+impl WalkForwardConfig {
+    /// The price data a fold starting on `start` trains on, only days before the fold are kept.
+    pub fn training_data(
+        &self,
+        companies: &CompaniesPriceData,
+        start: NaiveDate,
+    ) -> CompaniesPriceData {
+        let companies = companies
+            .iter()
+            .map(|company| {
+                let end = company
+                    .price_data
+                    .iter()
+                    .position(|item| item.date >= start)
+                    .unwrap_or(company.price_data.len());
+                let begin = match self.window {
+                    WalkForwardWindow::Rolling => end.saturating_sub(self.train_days),
+                    WalkForwardWindow::Expanding => 0,
+                };
+
+                CompanyPriceData {
+                    price_data: company.price_data[begin..end].to_vec(),
+                    ..company.clone()
+                }
+            })
+            .collect();
+
+        CompaniesPriceData { companies }
+    }
+
+    /// The cutoff of the chronological split of a fold's training data. The last `step_days` windows before the
+    /// fold validate the fold's model, after the embargo that keeps them apart from the training windows. None
+    /// when the history is too short, the split then falls back to `split_val`.
+    pub fn split_date(
+        &self,
+        training: &CompaniesPriceData,
+        window_size: usize,
+        prediction_interval: usize,
+    ) -> Option<NaiveDate> {
+        let dates: BTreeSet<NaiveDate> = training
+            .iter()
+            .flat_map(|company| company.price_data.iter().map(|item| item.date))
+            .collect();
+
+        // the validation windows span their days and target, and start after the embargo of the split.
+        let span = window_size + prediction_interval;
+        let embargo = window_size + prediction_interval + 1;
+        let holdout = self.step_days + span + embargo;
+
+        dates.iter().rev().nth(holdout - 1).cloned()
+    }
+}
+
+/// Stitches the value histories of consecutive folds into one equity curve, every fold starts from the value the
+/// previous fold ended with.
+pub fn stitch(folds: Vec<Vec<(NaiveDate, f64)>>, start_balance: f64) -> Vec<(NaiveDate, f64)> {
+    let mut stitched: Vec<(NaiveDate, f64)> = Vec::new();
+    for fold in folds {
+        let scale = stitched.last().map(|last| last.1).unwrap_or(start_balance) / start_balance;
+        stitched.extend(fold.into_iter().map(|(date, value)| (date, value * scale)));
+    }
+    stitched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ml_model::{data_loader::TrainPriceDataSetConfig, ExperimentConfig};
+    use crate::price_data::{testing, CalendarKind};
+    use burn::data::dataset::Dataset;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2020, 1, day).unwrap()
+    }
+
+    // One company with a row on every day from the 1st to the 20th of January 2020.
+    fn companies() -> CompaniesPriceData {
+        let price_data = (1..=20)
+            .map(|day| testing::item(date(day), 1.0, 1))
+            .collect();
+        CompaniesPriceData {
+            companies: vec![testing::company("A", price_data)],
+        }
+    }
+
+    fn dates(companies: &CompaniesPriceData) -> Vec<NaiveDate> {
+        companies.companies[0]
+            .price_data
+            .iter()
+            .map(|item| item.date)
+            .collect()
+    }
+
+    #[test]
+    fn expanding_window_trains_on_every_day_before_the_fold() {
+        let config = WalkForwardConfig::default();
+        let training = config.training_data(&companies(), date(15));
+        assert_eq!(dates(&training), (1..15).map(date).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn rolling_window_trains_on_the_last_days_before_the_fold() {
+        let config = WalkForwardConfig {
+            window: WalkForwardWindow::Rolling,
+            train_days: 5,
+            ..WalkForwardConfig::default()
+        };
+        let training = config.training_data(&companies(), date(15));
+        assert_eq!(dates(&training), (10..15).map(date).collect::<Vec<_>>());
+
+        // a fold with less history than `train_days` trains on all of it.
+        let training = config.training_data(&companies(), date(3));
+        assert_eq!(dates(&training), vec![date(1), date(2)]);
+
+        // a fold after the last row trains on the last rows.
+        let training = config.training_data(&companies(), date(31));
+        assert_eq!(dates(&training), (16..=20).map(date).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn stitched_folds_continue_from_the_previous_value() {
+        let folds = vec![
+            vec![(date(1), 128.0), (date(2), 256.0)],
+            vec![(date(3), 128.0), (date(4), 64.0)],
+            vec![],
+            vec![(date(5), 192.0)],
+        ];
+        let stitched = stitch(folds, 128.0);
+        assert_eq!(
+            stitched,
+            vec![
+                (date(1), 128.0),
+                (date(2), 256.0),
+                (date(3), 256.0),
+                (date(4), 128.0),
+                (date(5), 192.0),
+            ]
+        );
+        assert!(stitch(Vec::new(), 128.0).is_empty());
+    }
+
+    #[test]
+    fn folds_validate_on_the_days_before_them() {
+        let config = WalkForwardConfig {
+            step_days: 3,
+            ..WalkForwardConfig::default()
+        };
+        let training = config.training_data(&companies(), date(20));
+
+        // 3 validation windows of 2 days and their target 1 day later, after an embargo of 4 days.
+        let split_date = config.split_date(&training, 2, 1);
+        assert_eq!(split_date, Some(date(10)));
+
+        let experiment = ExperimentConfig {
+            window_size: 2,
+            prediction_interval: 1,
+            split_date: Some(date(2)),
+            ..ExperimentConfig::default()
+        };
+        let (train, test, _) = TrainPriceDataSetConfig::new(&experiment, CalendarKind::Derived)
+            .with_split_date(split_date)
+            .init(training, 1);
+        assert_eq!(test.len(), 3);
+        // the windows whose target is before the cutoff, the last target is the 9th.
+        assert_eq!(train.len(), 6);
+    }
+
+    #[test]
+    fn short_histories_fall_back_to_split_val() {
+        let config = WalkForwardConfig::default();
+        let training = config.training_data(&companies(), date(20));
+        assert_eq!(config.split_date(&training, 2, 1), None);
+    }
+}
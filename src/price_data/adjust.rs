@@ -0,0 +1,264 @@
+use std::fs::File;
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use super::{error::PriceDataError, CompanyPriceData, PriceDataItem};
+
+/// Selects which price series is used once the csv has been parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceAdjustment {
+    /// Prices exactly as they were quoted on the day.
+    #[default]
+    Raw,
+    /// Back-adjusts every price by the ratio of the vendor's adjusted close to the close.
+    AdjustedClose,
+    /// Back-adjusts every price from an explicit splits and dividends file,
+    /// `stock_market_data/{exchange}/actions/{symbol}.csv` with the columns `Date,Split,Dividend`.
+    CorporateActions,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AdjustmentConfig {
+    #[serde(default)]
+    pub dataset: PriceAdjustment,
+    #[serde(default)]
+    pub backtest: PriceAdjustment,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CorporateAction {
+    pub date: NaiveDate,
+    // The number of new shares per old share, 2.0 for a 2-for-1 split.
+    pub split: f64,
+    // The cash dividend per share paid on the ex-date.
+    pub dividend: f64,
+}
+
+impl CompanyPriceData {
+    pub fn adjust(&mut self, adjustment: PriceAdjustment) -> Result<(), PriceDataError> {
+        let factors = match adjustment {
+            PriceAdjustment::Raw => return Ok(()),
+            PriceAdjustment::AdjustedClose => AdjustmentFactors {
+                price: self
+                    .price_data
+                    .iter()
+                    .map(|item| item.adjusted_close / item.close)
+                    .collect(),
+                // the vendor's ratio does not tell splits from dividends, so volumes are left as quoted.
+                split: vec![1.0; self.price_data.len()],
+            },
+            PriceAdjustment::CorporateActions => {
+                back_adjustment_factors(&self.price_data, &self.fetch_corporate_actions()?)
+            }
+        };
+
+        factors.apply(&mut self.price_data);
+        Ok(())
+    }
+
+    // Companies without an actions file never split or paid a dividend.
+    fn fetch_corporate_actions(&self) -> Result<Vec<CorporateAction>, PriceDataError> {
+        let path = format!(
+            "stock_market_data/{exchange}/actions/{symbol}.csv",
+            exchange = self.exchange,
+            symbol = self.symbol
+        );
+
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(source) => return Err(PriceDataError::MissingFile { path, source }),
+        };
+
+        let mut rdr = csv::Reader::from_reader(file);
+        let mut actions = Vec::new();
+
+        for (num, result) in rdr.records().enumerate() {
+            let record = result.map_err(|source| PriceDataError::Csv {
+                symbol: self.symbol.clone(),
+                source,
+            })?;
+
+            let error = |field: &'static str, value: &str| PriceDataError::Parse {
+                symbol: self.symbol.clone(),
+                record: num,
+                field,
+                value: value.to_string(),
+            };
+
+            let date = NaiveDate::parse_from_str(&record[0], &self.schema.date_format)
+                .map_err(|_| error("action date", &record[0]))?;
+            let split = match record.get(1).unwrap_or("") {
+                "" => 1.0,
+                value => value.parse().map_err(|_| error("split", value))?,
+            };
+            let dividend = match record.get(2).unwrap_or("") {
+                "" => 0.0,
+                value => value.parse().map_err(|_| error("dividend", value))?,
+            };
+
+            actions.push(CorporateAction {
+                date,
+                split,
+                dividend,
+            });
+        }
+
+        Ok(actions)
+    }
+}
+
+/// The factors every row has to be multiplied by so that the series is continuous across splits and dividends.
+/// Factors are relative to the most recent row, which always has a factor of 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdjustmentFactors {
+    // Adjusts the prices for splits and dividends.
+    pub price: Vec<f64>,
+    // Adjusts the prices for splits only, volumes are divided by it.
+    pub split: Vec<f64>,
+}
+
+impl AdjustmentFactors {
+    fn apply(&self, price_data: &mut [PriceDataItem]) {
+        for (item, (factor, split)) in price_data
+            .iter_mut()
+            .zip(self.price.iter().zip(self.split.iter()))
+        {
+            // a zero close would give an infinite factor, these rows are left untouched.
+            if !factor.is_finite() || *factor <= 0.0 {
+                continue;
+            }
+            item.open *= factor;
+            item.high *= factor;
+            item.low *= factor;
+            item.close *= factor;
+            item.adjusted_close = item.close;
+            if split.is_finite() && *split > 0.0 {
+                item.volume = (item.volume as f64 / split).round() as i64;
+            }
+        }
+    }
+}
+
+/// Computes the adjustment factors of every row from the splits and dividends of the company.
+pub fn back_adjustment_factors(
+    price_data: &[PriceDataItem],
+    actions: &[CorporateAction],
+) -> AdjustmentFactors {
+    let mut factors = AdjustmentFactors {
+        price: vec![1.0; price_data.len()],
+        split: vec![1.0; price_data.len()],
+    };
+    let mut factor = 1.0;
+    let mut split_factor = 1.0;
+
+    // walk backwards so that an action applies to every row before its ex-date.
+    for idx in (0..price_data.len()).rev() {
+        factors.price[idx] = factor;
+        factors.split[idx] = split_factor;
+
+        let date = price_data[idx].date;
+        let prev_date = match idx {
+            0 => NaiveDate::MIN,
+            _ => price_data[idx - 1].date,
+        };
+
+        for action in actions
+            .iter()
+            .filter(|action| action.date > prev_date && action.date <= date)
+        {
+            if action.split > 0.0 {
+                factor /= action.split;
+                split_factor /= action.split;
+            }
+            if action.dividend > 0.0 && idx > 0 {
+                let prev_close = price_data[idx - 1].close;
+                if prev_close > action.dividend {
+                    factor *= 1.0 - action.dividend / prev_close;
+                }
+            }
+        }
+    }
+
+    factors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price_data::testing;
+
+    fn item(day: u32, close: f64, volume: i64) -> PriceDataItem {
+        testing::item(
+            NaiveDate::from_ymd_opt(2001, 1, day).unwrap(),
+            close,
+            volume,
+        )
+    }
+
+    fn action(day: u32, split: f64, dividend: f64) -> CorporateAction {
+        CorporateAction {
+            date: NaiveDate::from_ymd_opt(2001, 1, day).unwrap(),
+            split,
+            dividend,
+        }
+    }
+
+    #[test]
+    fn split_adjusts_prices_and_volumes_before_the_ex_date() {
+        let mut price_data = vec![item(2, 100.0, 10), item(3, 102.0, 10), item(4, 51.0, 20)];
+        let factors = back_adjustment_factors(&price_data, &[action(4, 2.0, 0.0)]);
+
+        assert_eq!(factors.price, vec![0.5, 0.5, 1.0]);
+        assert_eq!(factors.split, vec![0.5, 0.5, 1.0]);
+
+        factors.apply(&mut price_data);
+        let closes: Vec<f64> = price_data.iter().map(|item| item.close).collect();
+        let volumes: Vec<i64> = price_data.iter().map(|item| item.volume).collect();
+        assert_eq!(closes, vec![50.0, 51.0, 51.0]);
+        assert_eq!(volumes, vec![20, 20, 20]);
+    }
+
+    #[test]
+    fn dividend_adjusts_prices_but_not_volumes() {
+        let mut price_data = vec![item(2, 100.0, 10), item(3, 100.0, 10), item(4, 98.0, 10)];
+        let factors = back_adjustment_factors(&price_data, &[action(4, 1.0, 2.0)]);
+
+        assert_eq!(factors.price, vec![0.98, 0.98, 1.0]);
+        assert_eq!(factors.split, vec![1.0, 1.0, 1.0]);
+
+        factors.apply(&mut price_data);
+        assert_eq!(price_data[0].close, 98.0);
+        assert_eq!(price_data[0].adjusted_close, 98.0);
+        assert!(price_data.iter().all(|item| item.volume == 10));
+    }
+
+    #[test]
+    fn actions_between_rows_apply_to_the_next_row() {
+        let price_data = vec![item(2, 100.0, 10), item(5, 50.0, 20)];
+        let factors = back_adjustment_factors(&price_data, &[action(3, 2.0, 0.0)]);
+
+        assert_eq!(factors.price, vec![0.5, 1.0]);
+    }
+
+    #[test]
+    fn adjusted_close_keeps_the_volume() {
+        let mut company = testing::company(
+            "TEST",
+            vec![
+                PriceDataItem {
+                    adjusted_close: 50.0,
+                    ..item(2, 100.0, 10)
+                },
+                item(3, 50.0, 10),
+            ],
+        );
+        company.adjust(PriceAdjustment::AdjustedClose).unwrap();
+
+        assert_eq!(company.price_data[0].close, 50.0);
+        assert_eq!(company.price_data[0].open, 50.0);
+        assert!(company.price_data.iter().all(|item| item.volume == 10));
+    }
+}
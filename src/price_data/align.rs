@@ -0,0 +1,236 @@
+use std::collections::{BTreeSet, HashSet};
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use super::{CompaniesPriceData, PriceDataItem};
+
+/// Decides how a company's missing days on the master calendar are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GapFill {
+    /// Insert the missing day with every price set to the previous close and no volume.
+    ForwardFill,
+    /// Insert the missing day with NaN prices, windows that contain it are skipped downstream.
+    MarkMissing,
+    /// Remove the day from every company, so only days where all listed companies traded remain.
+    Drop,
+}
+
+#[derive(Debug, Default)]
+pub struct AlignmentReport {
+    pub calendar_days: usize,
+    pub filled_rows: usize,
+    pub dropped_days: usize,
+}
+
+impl PriceDataItem {
+    pub fn missing(date: NaiveDate) -> PriceDataItem {
+        PriceDataItem {
+            date,
+            open: f64::NAN,
+            high: f64::NAN,
+            low: f64::NAN,
+            close: f64::NAN,
+            volume: 0,
+            adjusted_close: f64::NAN,
+        }
+    }
+
+    pub fn forward_filled(date: NaiveDate, prev: &PriceDataItem) -> PriceDataItem {
+        PriceDataItem {
+            date,
+            open: prev.close,
+            high: prev.close,
+            low: prev.close,
+            close: prev.close,
+            volume: 0,
+            adjusted_close: prev.adjusted_close,
+        }
+    }
+
+    /// Rows inserted by `GapFill::MarkMissing` have no prices.
+    pub fn is_missing(&self) -> bool {
+        !self.close.is_finite()
+    }
+}
+
+impl CompaniesPriceData {
+    /// Aligns every company to the master calendar, the trading days of the universe's `TradingCalendar`,
+    /// between the company's own first and last trading day.
+    /// Days before a company listed or after it delisted are never filled.
+    pub fn align(&mut self, calendar: &BTreeSet<NaiveDate>, gap_fill: GapFill) -> AlignmentReport {
+        let mut report = AlignmentReport::default();
+
+        if gap_fill == GapFill::Drop {
+            let listings: Vec<(NaiveDate, NaiveDate, HashSet<NaiveDate>)> = self
+                .companies
+                .iter()
+                .filter(|company| !company.price_data.is_empty())
+                .map(|company| {
+                    let dates: HashSet<NaiveDate> =
+                        company.price_data.iter().map(|item| item.date).collect();
+                    let first = company.price_data.first().unwrap().date;
+                    let last = company.price_data.last().unwrap().date;
+                    (first, last, dates)
+                })
+                .collect();
+
+            // a day is dropped when any company that was listed at the time has no data for it.
+            let dropped: HashSet<NaiveDate> = calendar
+                .iter()
+                .filter(|date| {
+                    listings.iter().any(|(first, last, dates)| {
+                        first <= *date && *date <= last && !dates.contains(*date)
+                    })
+                })
+                .cloned()
+                .collect();
+
+            for company in self.companies.iter_mut() {
+                company
+                    .price_data
+                    .retain(|item| calendar.contains(&item.date) && !dropped.contains(&item.date));
+            }
+
+            report.calendar_days = calendar.len() - dropped.len();
+            report.dropped_days = dropped.len();
+            return report;
+        }
+
+        for company in self.companies.iter_mut() {
+            let (first, last) = match (company.price_data.first(), company.price_data.last()) {
+                (Some(first), Some(last)) => (first.date, last.date),
+                _ => continue,
+            };
+
+            let mut rows = company.price_data.iter().peekable();
+            let mut aligned: Vec<PriceDataItem> = Vec::with_capacity(company.price_data.len());
+
+            for date in calendar.range(first..=last) {
+                // rows that are not on the calendar are dropped.
+                while rows.peek().is_some_and(|row| row.date < *date) {
+                    rows.next();
+                }
+
+                match rows.peek() {
+                    Some(row) if row.date == *date => {
+                        aligned.push(**row);
+                        rows.next();
+                    }
+                    _ => {
+                        let filled = match (gap_fill, aligned.last()) {
+                            (GapFill::ForwardFill, Some(prev)) => {
+                                PriceDataItem::forward_filled(*date, prev)
+                            }
+                            _ => PriceDataItem::missing(*date),
+                        };
+                        aligned.push(filled);
+                        report.filled_rows += 1;
+                    }
+                }
+            }
+
+            company.price_data = aligned;
+        }
+
+        report.calendar_days = calendar.len();
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price_data::{testing, CompanyPriceData};
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2001, 1, day).unwrap()
+    }
+
+    fn company(symbol: &str, days: &[u32]) -> CompanyPriceData {
+        let price_data = days
+            .iter()
+            .map(|day| testing::item(date(*day), *day as f64, 100))
+            .collect();
+        testing::company(symbol, price_data)
+    }
+
+    fn universe() -> CompaniesPriceData {
+        CompaniesPriceData {
+            companies: vec![company("A", &[2, 3, 4, 5]), company("B", &[3, 5, 8])],
+        }
+    }
+
+    fn calendar() -> BTreeSet<NaiveDate> {
+        [2, 3, 4, 5, 8].into_iter().map(date).collect()
+    }
+
+    fn dates(company: &CompanyPriceData) -> Vec<NaiveDate> {
+        company.price_data.iter().map(|item| item.date).collect()
+    }
+
+    #[test]
+    fn forward_fill_inserts_the_previous_close() {
+        let mut companies = universe();
+        let report = companies.align(&calendar(), GapFill::ForwardFill);
+
+        let b = &companies.companies[1];
+        assert_eq!(dates(b), vec![date(3), date(4), date(5), date(8)]);
+        assert_eq!(b.price_data[1].close, 3.0);
+        assert_eq!(b.price_data[1].volume, 0);
+        assert_eq!(report.filled_rows, 1);
+    }
+
+    #[test]
+    fn filling_stays_within_the_listing() {
+        let mut companies = universe();
+        companies.align(&calendar(), GapFill::ForwardFill);
+
+        // A is not filled after its last day and B is not filled before its first day.
+        assert_eq!(dates(&companies.companies[0]).last(), Some(&date(5)));
+        assert_eq!(dates(&companies.companies[1]).first(), Some(&date(3)));
+    }
+
+    #[test]
+    fn mark_missing_inserts_rows_without_prices() {
+        let mut companies = universe();
+        companies.align(&calendar(), GapFill::MarkMissing);
+
+        let b = &companies.companies[1];
+        assert!(b.price_data[1].is_missing());
+        assert!(!b.price_data[0].is_missing());
+    }
+
+    #[test]
+    fn drop_removes_days_a_listed_company_is_missing() {
+        let mut companies = universe();
+        let report = companies.align(&calendar(), GapFill::Drop);
+
+        assert_eq!(
+            dates(&companies.companies[0]),
+            vec![date(2), date(3), date(5)]
+        );
+        assert_eq!(
+            dates(&companies.companies[1]),
+            vec![date(3), date(5), date(8)]
+        );
+        assert_eq!(report.dropped_days, 1);
+        assert_eq!(report.calendar_days, 4);
+    }
+
+    #[test]
+    fn rows_off_the_calendar_are_dropped() {
+        let mut companies = CompaniesPriceData {
+            companies: vec![company("A", &[2, 3, 6, 8])],
+        };
+        companies.align(&calendar(), GapFill::ForwardFill);
+
+        // the 6th is a Saturday, the 4th and 5th are filled from the 3rd.
+        assert_eq!(
+            dates(&companies.companies[0]),
+            vec![date(2), date(3), date(4), date(5), date(8)]
+        );
+        assert_eq!(companies.companies[0].price_data[3].close, 3.0);
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::Path,
+    time::SystemTime,
+};
+
+use chrono::{Datelike, NaiveDate};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::gather_companies, load::progress_bar, schema::CsvSchema, CompaniesPriceData,
+    LoadReport, PriceDataError, PriceDataItem,
+};
+
+/// The parsed contents of a single csv file, stored column by column.
+/// A cache entry is only valid for the exact source file, modification time and schema it was built from.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PriceDataCache {
+    source_path: String,
+    modified: SystemTime,
+    schema: CsvSchema,
+    dates: Vec<i32>,
+    open: Vec<f64>,
+    high: Vec<f64>,
+    low: Vec<f64>,
+    close: Vec<f64>,
+    volume: Vec<i64>,
+    adjusted_close: Vec<f64>,
+    // The malformed rows of the csv file that were skipped while parsing it.
+    skipped_rows: usize,
+}
+
+impl PriceDataCache {
+    pub fn new(
+        source_path: String,
+        modified: SystemTime,
+        schema: CsvSchema,
+        price_data: &[PriceDataItem],
+        skipped_rows: usize,
+    ) -> PriceDataCache {
+        PriceDataCache {
+            source_path,
+            modified,
+            schema,
+            dates: price_data
+                .iter()
+                .map(|item| item.date.num_days_from_ce())
+                .collect(),
+            open: price_data.iter().map(|item| item.open).collect(),
+            high: price_data.iter().map(|item| item.high).collect(),
+            low: price_data.iter().map(|item| item.low).collect(),
+            close: price_data.iter().map(|item| item.close).collect(),
+            volume: price_data.iter().map(|item| item.volume).collect(),
+            adjusted_close: price_data.iter().map(|item| item.adjusted_close).collect(),
+            skipped_rows,
+        }
+    }
+
+    pub fn skipped_rows(&self) -> usize {
+        self.skipped_rows
+    }
+
+    pub fn is_valid_for(
+        &self,
+        source_path: &str,
+        modified: SystemTime,
+        schema: &CsvSchema,
+    ) -> bool {
+        self.source_path == source_path && self.modified == modified && &self.schema == schema
+    }
+
+    pub fn price_data(&self, start: NaiveDate, end: NaiveDate) -> Vec<PriceDataItem> {
+        let mut price_data = Vec::new();
+        for idx in 0..self.dates.len() {
+            let date = match NaiveDate::from_num_days_from_ce_opt(self.dates[idx]) {
+                Some(date) => date,
+                None => continue,
+            };
+            if date < start || date > end {
+                continue;
+            }
+            price_data.push(PriceDataItem {
+                date,
+                open: self.open[idx],
+                high: self.high[idx],
+                low: self.low[idx],
+                close: self.close[idx],
+                volume: self.volume[idx],
+                adjusted_close: self.adjusted_close[idx],
+            });
+        }
+        price_data
+    }
+
+    // A missing or unreadable cache file is treated the same as a stale one, the csv is parsed again.
+    pub fn read(path: &str) -> Option<PriceDataCache> {
+        let file = File::open(path).ok()?;
+        bincode::deserialize_from(BufReader::new(file)).ok()
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), String> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let file = File::create(path).map_err(|err| err.to_string())?;
+        bincode::serialize_into(BufWriter::new(file), self).map_err(|err| err.to_string())
+    }
+}
+
+pub fn cache_path(exchange: &str, symbol: &str) -> String {
+    format!("stock_market_data/.cache/{exchange}/{symbol}.bin")
+}
+
+/// Parses every csv file of an exchange and writes its cache entry, so that later runs skip parsing entirely.
+pub fn build_exchange_cache(
+    exchange: &str,
+    schema: CsvSchema,
+) -> Result<LoadReport, PriceDataError> {
+    let companies: CompaniesPriceData =
+        gather_companies(vec!["all".to_string()], exchange.to_string(), schema)?;
+    let progress = progress_bar(companies.companies.len(), "Caching data");
+
+    let results: Vec<_> = companies
+        .companies
+        .into_par_iter()
+        .map(|mut company| {
+            let result = company.refresh_data(NaiveDate::MIN, NaiveDate::MAX, true);
+            progress.inc(1);
+            // the parsed data is only needed on disk, so the company is dropped here.
+            (company.company(), result)
+        })
+        .collect();
+
+    progress.finish_with_message(format!("Cached {exchange}"));
+
+    let mut report = LoadReport::new();
+    for (company, result) in results {
+        match result {
+            Ok(skipped) => {
+                if skipped > 0 {
+                    report.malformed_rows.push((company.clone(), skipped));
+                }
+                report.loaded.push(company);
+            }
+            Err(err) => report.skipped.push((company, err)),
+        }
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,271 @@
+use std::{
+    collections::BTreeSet,
+    ops::Bound::{Excluded, Unbounded},
+};
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use serde::Deserialize;
+
+use super::CompaniesPriceData;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CalendarKind {
+    /// Weekdays that are not an NYSE holiday or an unscheduled closure.
+    #[default]
+    Nyse,
+    /// Every day that at least one company in the universe has price data for.
+    Derived,
+}
+
+#[derive(Debug, Clone)]
+pub enum TradingCalendar {
+    Nyse,
+    Derived(BTreeSet<NaiveDate>),
+}
+
+impl TradingCalendar {
+    pub fn new(kind: CalendarKind, companies: &CompaniesPriceData) -> TradingCalendar {
+        match kind {
+            CalendarKind::Nyse => TradingCalendar::Nyse,
+            CalendarKind::Derived => TradingCalendar::Derived(
+                companies
+                    .iter()
+                    .flat_map(|company| company.price_data.iter().map(|item| item.date))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn is_trading_day(&self, date: NaiveDate) -> bool {
+        match self {
+            TradingCalendar::Nyse => {
+                !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !is_nyse_holiday(date)
+            }
+            TradingCalendar::Derived(days) => days.contains(&date),
+        }
+    }
+
+    /// The first trading day after `date`, a derived calendar has no trading days after its last date.
+    pub fn next_trading_day(&self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            TradingCalendar::Nyse => {
+                let mut next = date.succ_opt()?;
+                while !self.is_trading_day(next) {
+                    next = next.succ_opt()?;
+                }
+                Some(next)
+            }
+            TradingCalendar::Derived(days) => {
+                days.range((Excluded(date), Unbounded)).next().cloned()
+            }
+        }
+    }
+
+    /// Every trading day from `start` to `end`, both inclusive.
+    pub fn trading_days(&self, start: NaiveDate, end: NaiveDate) -> BTreeSet<NaiveDate> {
+        match self {
+            TradingCalendar::Nyse => start
+                .iter_days()
+                .take_while(|date| *date <= end)
+                .filter(|date| self.is_trading_day(*date))
+                .collect(),
+            TradingCalendar::Derived(days) => {
+                if start > end {
+                    return BTreeSet::new();
+                }
+                days.range(start..=end).cloned().collect()
+            }
+        }
+    }
+}
+
+fn is_nyse_holiday(date: NaiveDate) -> bool {
+    nyse_holidays(date.year()).contains(&date) || NYSE_CLOSURES.contains(&ymd_tuple(date))
+}
+
+fn ymd_tuple(date: NaiveDate) -> (i32, u32, u32) {
+    (date.year(), date.month(), date.day())
+}
+
+// Unscheduled closures, national days of mourning and weather events.
+const NYSE_CLOSURES: [(i32, u32, u32); 12] = [
+    (1985, 9, 27),
+    (1994, 4, 27),
+    (2001, 9, 11),
+    (2001, 9, 12),
+    (2001, 9, 13),
+    (2001, 9, 14),
+    (2004, 6, 11),
+    (2007, 1, 2),
+    (2012, 10, 29),
+    (2012, 10, 30),
+    (2018, 12, 5),
+    (2025, 1, 9),
+];
+
+/// The full day holidays the NYSE observes in `year`.
+pub fn nyse_holidays(year: i32) -> Vec<NaiveDate> {
+    let mut holidays = Vec::new();
+
+    // New Year's Day moves to Monday when it falls on a Sunday, but is not observed on the Friday before when it
+    // falls on a Saturday, as that Friday closes the accounting year.
+    let new_year = ymd(year, 1, 1);
+    match new_year.weekday() {
+        Weekday::Sat => {}
+        Weekday::Sun => holidays.push(ymd(year, 1, 2)),
+        _ => holidays.push(new_year),
+    }
+
+    if year >= 1998 {
+        holidays.push(nth_weekday(year, 1, Weekday::Mon, 3)); // Martin Luther King Jr. Day
+    }
+    holidays.push(nth_weekday(year, 2, Weekday::Mon, 3)); // Washington's Birthday
+    holidays.push(easter_sunday(year) - Days::new(2)); // Good Friday
+    holidays.push(last_weekday(year, 5, Weekday::Mon)); // Memorial Day
+    if year >= 2022 {
+        holidays.push(observed(ymd(year, 6, 19))); // Juneteenth
+    }
+    holidays.push(observed(ymd(year, 7, 4))); // Independence Day
+    holidays.push(nth_weekday(year, 9, Weekday::Mon, 1)); // Labor Day
+    holidays.push(nth_weekday(year, 11, Weekday::Thu, 4)); // Thanksgiving
+    holidays.push(observed(ymd(year, 12, 25))); // Christmas
+
+    holidays
+}
+
+fn ymd(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+// Holidays on a Saturday are observed on the Friday before, holidays on a Sunday on the Monday after.
+fn observed(date: NaiveDate) -> NaiveDate {
+    match date.weekday() {
+        Weekday::Sat => date.pred_opt().unwrap(),
+        Weekday::Sun => date.succ_opt().unwrap(),
+        _ => date,
+    }
+}
+
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: u8) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, n).unwrap()
+}
+
+fn last_weekday(year: i32, month: u32, weekday: Weekday) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, 5)
+        .unwrap_or_else(|| nth_weekday(year, month, weekday, 4))
+}
+
+// The anonymous Gregorian algorithm for the date of Easter Sunday.
+fn easter_sunday(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    ymd(year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price_data::{testing, PriceDataItem};
+
+    #[test]
+    fn nyse_holidays_of_2024() {
+        let holidays = nyse_holidays(2024);
+
+        assert_eq!(
+            holidays,
+            vec![
+                ymd(2024, 1, 1),
+                ymd(2024, 1, 15),
+                ymd(2024, 2, 19),
+                ymd(2024, 3, 29),
+                ymd(2024, 5, 27),
+                ymd(2024, 6, 19),
+                ymd(2024, 7, 4),
+                ymd(2024, 9, 2),
+                ymd(2024, 11, 28),
+                ymd(2024, 12, 25),
+            ]
+        );
+    }
+
+    #[test]
+    fn weekend_holidays_are_observed() {
+        // Independence Day 2020 was a Saturday, Christmas 2022 a Sunday.
+        assert!(nyse_holidays(2020).contains(&ymd(2020, 7, 3)));
+        assert!(nyse_holidays(2022).contains(&ymd(2022, 12, 26)));
+        // New Year's Day 2022 was a Saturday and was not observed on the Friday before.
+        assert!(!nyse_holidays(2022).contains(&ymd(2021, 12, 31)));
+        assert!(!nyse_holidays(2021).contains(&ymd(2021, 12, 31)));
+    }
+
+    #[test]
+    fn good_friday_follows_easter() {
+        assert_eq!(easter_sunday(2019), ymd(2019, 4, 21));
+        assert_eq!(easter_sunday(2024), ymd(2024, 3, 31));
+        assert!(nyse_holidays(2019).contains(&ymd(2019, 4, 19)));
+    }
+
+    #[test]
+    fn nyse_skips_weekends_holidays_and_closures() {
+        let calendar = TradingCalendar::Nyse;
+
+        // Thanksgiving 2024 is followed by a Friday session.
+        assert_eq!(
+            calendar.next_trading_day(ymd(2024, 11, 27)),
+            Some(ymd(2024, 11, 29))
+        );
+        assert_eq!(
+            calendar.next_trading_day(ymd(2024, 11, 29)),
+            Some(ymd(2024, 12, 2))
+        );
+        assert!(!calendar.is_trading_day(ymd(2001, 9, 11)));
+        assert!(!calendar.is_trading_day(ymd(2012, 10, 29)));
+        assert_eq!(
+            calendar
+                .trading_days(ymd(2024, 12, 23), ymd(2024, 12, 27))
+                .len(),
+            4
+        );
+    }
+
+    #[test]
+    fn derived_calendar_uses_the_days_with_data() {
+        let company = testing::company(
+            "TEST",
+            [ymd(2024, 1, 2), ymd(2024, 1, 6), ymd(2024, 1, 9)]
+                .into_iter()
+                .map(PriceDataItem::missing)
+                .collect(),
+        );
+        let calendar = TradingCalendar::new(
+            CalendarKind::Derived,
+            &CompaniesPriceData {
+                companies: vec![company],
+            },
+        );
+
+        assert!(calendar.is_trading_day(ymd(2024, 1, 6)));
+        assert!(!calendar.is_trading_day(ymd(2024, 1, 3)));
+        assert_eq!(
+            calendar.next_trading_day(ymd(2024, 1, 2)),
+            Some(ymd(2024, 1, 6))
+        );
+        assert_eq!(calendar.next_trading_day(ymd(2024, 1, 9)), None);
+        assert!(calendar
+            .trading_days(ymd(2024, 1, 9), ymd(2024, 1, 2))
+            .is_empty());
+    }
+}
@@ -1,9 +1,18 @@
 use std::{
+    collections::{BTreeMap, HashMap, HashSet},
     error::Error,
-    fs::{self, File},
+    fs,
+    path::Path,
 };
 
-use super::{CompaniesPriceData, CompanyPriceData};
+use super::{
+    adjust::AdjustmentConfig,
+    calendar::CalendarKind,
+    schema::{CsvSchema, CsvSchemaJson},
+    CompaniesPriceData, CompanyPriceData, GapFill, LoadOptions, LoadPolicy, PriceAdjustment,
+    PriceDataError, TradingCalendar, ValidationConfig,
+};
+use crate::ml_model::ExperimentConfig;
 use chrono::NaiveDate;
 use serde::{Deserialize, Deserializer};
 
@@ -15,26 +24,117 @@ pub struct DataConfig {
     pub validate_start: NaiveDate,
     pub validate_end: NaiveDate,
     pub validate_companies: CompaniesPriceData,
+    // An optional out-of-sample universe that is backtested after validation.
+    pub test: Option<TestUniverse>,
+    pub load_policy: LoadPolicy,
+    // The training windows and the backtest can each be built from raw or adjusted prices.
+    pub dataset_adjustment: PriceAdjustment,
+    pub backtest_adjustment: PriceAdjustment,
+    pub cache: bool,
+    pub validation: ValidationConfig,
+    // Without a gap fill every company keeps its own set of dates.
+    pub alignment: Option<GapFill>,
+    pub calendar: CalendarKind,
+    pub sources: HashMap<String, CsvSchema>,
+    pub artifact_dir: String,
+    pub experiment: ExperimentConfig,
 }
 
 impl DataConfig {
-    pub fn init(&mut self) -> Self {
+    pub fn init(&mut self) -> Result<Self, PriceDataError> {
         println!("Fetching price data...");
 
-        self.train_companies
-            .refresh_data(self.train_start, self.train_end);
-        self.validate_companies
-            .refresh_data(self.validate_start, self.validate_end);
+        let mut train_companies = std::mem::take(&mut self.train_companies);
+        self.load_universe(
+            "Training",
+            &mut train_companies,
+            self.train_start,
+            self.train_end,
+            self.dataset_adjustment,
+        )?;
+        self.train_companies = train_companies;
+
+        let mut validate_companies = std::mem::take(&mut self.validate_companies);
+        self.load_universe(
+            "Validation",
+            &mut validate_companies,
+            self.validate_start,
+            self.validate_end,
+            self.backtest_adjustment,
+        )?;
+        self.validate_companies = validate_companies;
+
+        if let Some(mut test) = self.test.take() {
+            self.load_universe(
+                "Test",
+                &mut test.companies,
+                test.start,
+                test.end,
+                self.backtest_adjustment,
+            )?;
+            self.test = Some(test);
+        }
 
-        self.to_owned()
+        Ok(self.to_owned())
     }
 
-    pub fn new() -> Result<DataConfig, Box<dyn Error>> {
-        let file_path = "config.json";
-        let config_file = File::open(file_path)?;
-        let data_config: DataConfig = serde_json::from_reader(config_file)?;
+    fn load_universe(
+        &self,
+        name: &str,
+        companies: &mut CompaniesPriceData,
+        start: NaiveDate,
+        end: NaiveDate,
+        adjustment: PriceAdjustment,
+    ) -> Result<(), PriceDataError> {
+        let report = companies.refresh_data(
+            start,
+            end,
+            LoadOptions {
+                policy: self.load_policy,
+                adjustment,
+                cache: self.cache,
+                validation: self.validation,
+            },
+        )?;
+        println!("{name} data: {report}");
+
+        if let Some(gap_fill) = self.alignment {
+            let calendar = TradingCalendar::new(self.calendar, companies);
+            let report = companies.align(&calendar.trading_days(start, end), gap_fill);
+            println!(
+                "{name} data aligned to {days} trading days, filled {filled} rows, dropped {dropped} days",
+                days = report.calendar_days,
+                filled = report.filled_rows,
+                dropped = report.dropped_days
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Reads the configuration file, json, toml or yaml by its extension, then applies the environment overrides.
+    pub fn new(file_path: &str) -> Result<DataConfig, Box<dyn Error>> {
+        let contents = fs::read_to_string(file_path)
+            .map_err(|err| format!("could not read config file {file_path}: {err}"))?;
+
+        let mut value: serde_json::Value = match Path::new(file_path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("toml") => toml::from_str(&contents)?,
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&contents)?,
+            _ => serde_json::from_str(&contents)?,
+        };
+
+        apply_env_overrides(&mut value, std::env::vars())?;
+
+        let data_config: DataConfig = serde_json::from_value(value)?;
         return Ok(data_config);
     }
+
+    pub fn schema_for(&self, exchange: &str) -> CsvSchema {
+        self.sources.get(exchange).cloned().unwrap_or_default()
+    }
 }
 
 impl<'de> Deserialize<'de> for DataConfig {
@@ -43,42 +143,179 @@ impl<'de> Deserialize<'de> for DataConfig {
         D: Deserializer<'de>,
     {
         let data = DataConfigJson::deserialize(deserializer)?;
+        data.experiment
+            .validate()
+            .map_err(serde::de::Error::custom)?;
         let dates = data.dates;
         let train_start = dates.train_start;
         let train_end = dates.train_end;
         let validate_start = dates.valid_start;
         let validate_end = dates.valid_end;
 
-        let mut companies = CompaniesPriceData::new();
+        if let Some(split_date) = data.experiment.split_date {
+            if split_date <= train_start || split_date >= train_end {
+                return Err(serde::de::Error::custom(format!(
+                    "experiment.split_date must be between dates.train_start and dates.train_end, found {split_date}"
+                )));
+            }
+        }
 
-        let forbes2000 = gather_companies(data.forbes2000, "forbes2000".to_string());
-        let nasdaq = gather_companies(data.nasdaq, "nasdaq".to_string());
-        let nyse = gather_companies(data.nyse, "nyse".to_string());
-        let sp500 = gather_companies(data.sp500, "sp500".to_string());
+        let mut sources = HashMap::new();
+        for (exchange, schema) in data.sources {
+            let schema = schema.into_schema().map_err(serde::de::Error::custom)?;
+            sources.insert(exchange, schema);
+        }
+        let schema_for = |exchange: &str| sources.get(exchange).cloned().unwrap_or_default();
 
-        companies.append(forbes2000);
-        companies.append(nasdaq);
-        companies.append(nyse);
-        companies.append(sp500);
+        // The legacy layout lists tickers per exchange at the top level and uses them for training and validation.
+        let legacy = UniverseJson {
+            exchanges: Vec::new(),
+            tickers: BTreeMap::from([
+                ("forbes2000".to_string(), data.forbes2000),
+                ("nasdaq".to_string(), data.nasdaq),
+                ("nyse".to_string(), data.nyse),
+                ("sp500".to_string(), data.sp500),
+            ]),
+            exclude: Vec::new(),
+        };
+
+        let (train, validate, test) = match data.universes {
+            Some(_) if legacy.tickers.values().any(|tickers| !tickers.is_empty()) => {
+                return Err(serde::de::Error::custom(
+                    "universes replaces the top level forbes2000, nasdaq, nyse and sp500 lists, set only one of them",
+                ))
+            }
+            Some(universes) => (universes.train, universes.validate, universes.test),
+            None => (legacy.clone(), legacy, None),
+        };
+
+        let test = match (test, dates.test_start, dates.test_end) {
+            (None, _, _) => None,
+            (Some(test), Some(start), Some(end)) => Some(TestUniverse {
+                start,
+                end,
+                companies: test.gather(&schema_for).map_err(serde::de::Error::custom)?,
+            }),
+            (Some(_), _, _) => {
+                return Err(serde::de::Error::custom(
+                    "a test universe requires dates.test_start and dates.test_end",
+                ))
+            }
+        };
 
         return Ok(DataConfig {
             train_start,
             train_end,
             validate_start,
             validate_end,
-            train_companies: companies.clone(),
-            validate_companies: companies,
+            train_companies: train
+                .gather(&schema_for)
+                .map_err(serde::de::Error::custom)?,
+            validate_companies: validate
+                .gather(&schema_for)
+                .map_err(serde::de::Error::custom)?,
+            test,
+            load_policy: data.load_policy,
+            dataset_adjustment: data.adjustment.dataset,
+            backtest_adjustment: data.adjustment.backtest,
+            cache: data.cache,
+            validation: data.validation,
+            alignment: data.alignment,
+            calendar: data.calendar,
+            sources,
+            artifact_dir: data.artifact_dir,
+            experiment: data.experiment,
         });
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct TestUniverse {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub companies: CompaniesPriceData,
+}
+
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct DataConfigJson {
     pub dates: DataConfigDates,
+    #[serde(default)]
     pub forbes2000: Vec<String>,
+    #[serde(default)]
     pub nasdaq: Vec<String>,
+    #[serde(default)]
     pub nyse: Vec<String>,
+    #[serde(default)]
     pub sp500: Vec<String>,
+    // When present, replaces the top level exchange lists with independent universes.
+    #[serde(default)]
+    pub universes: Option<UniversesJson>,
+    // Maps an exchange folder to the csv layout of its files, exchanges that are not listed use the kaggle layout.
+    #[serde(default)]
+    pub sources: HashMap<String, CsvSchemaJson>,
+    #[serde(default)]
+    pub load_policy: LoadPolicy,
+    #[serde(default)]
+    pub adjustment: AdjustmentConfig,
+    // Parsed csv files are cached under `stock_market_data/.cache` unless this is turned off.
+    #[serde(default = "default_cache")]
+    pub cache: bool,
+    #[serde(default)]
+    pub validation: ValidationConfig,
+    #[serde(default)]
+    pub alignment: Option<GapFill>,
+    #[serde(default)]
+    pub calendar: CalendarKind,
+    // Where the trained model and its configuration are saved.
+    #[serde(default = "default_artifact_dir")]
+    pub artifact_dir: String,
+    // Model and backtest hyperparameters, saved next to the trained model as `experiment.json`.
+    #[serde(default)]
+    pub experiment: ExperimentConfig,
+}
+
+fn default_cache() -> bool {
+    true
+}
+
+fn default_artifact_dir() -> String {
+    "tmp/stock_predictor".to_string()
+}
+
+/// Environment variables starting with `ML_STOCK__` override a value of the configuration file.
+/// Nested keys are separated by a double underscore, `ML_STOCK__DATES__TRAIN_START=2005-01-01`
+/// replaces `dates.train_start`. Values are read as json when they parse as json and as a string otherwise.
+pub fn apply_env_overrides(
+    value: &mut serde_json::Value,
+    vars: impl Iterator<Item = (String, String)>,
+) -> Result<(), String> {
+    let prefix = "ML_STOCK__";
+
+    for (key, raw) in vars {
+        let path = match key.strip_prefix(prefix) {
+            Some(path) => path.to_lowercase(),
+            None => continue,
+        };
+
+        let mut target = &mut *value;
+        for segment in path.split("__") {
+            if !target.is_object() {
+                return Err(format!(
+                    "cannot apply {key}, \"{segment}\" is not inside an object"
+                ));
+            }
+            target = target
+                .as_object_mut()
+                .unwrap()
+                .entry(segment)
+                .or_insert(serde_json::Value::Object(serde_json::Map::new()));
+        }
+
+        *target = serde_json::from_str(&raw).unwrap_or(serde_json::Value::String(raw));
+    }
+
+    Ok(())
 }
 
 #[derive(Debug, Deserialize)]
@@ -87,28 +324,231 @@ pub struct DataConfigDates {
     pub train_end: NaiveDate,
     pub valid_start: NaiveDate,
     pub valid_end: NaiveDate,
+    #[serde(default)]
+    pub test_start: Option<NaiveDate>,
+    #[serde(default)]
+    pub test_end: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UniversesJson {
+    pub train: UniverseJson,
+    pub validate: UniverseJson,
+    #[serde(default)]
+    pub test: Option<UniverseJson>,
 }
 
-pub fn gather_companies(data: Vec<String>, exchange: String) -> CompaniesPriceData {
+#[derive(Debug, Clone, Deserialize)]
+pub struct UniverseJson {
+    // Exchanges whose every company is part of the universe.
+    #[serde(default)]
+    pub exchanges: Vec<String>,
+    // Tickers per exchange, `"all"` includes every company of the exchange.
+    #[serde(default)]
+    pub tickers: BTreeMap<String, Vec<String>>,
+    // Tickers removed from the universe, either `"SYMBOL"` for every exchange or `"exchange/SYMBOL"`.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl UniverseJson {
+    pub fn gather(
+        &self,
+        schema_for: &dyn Fn(&str) -> CsvSchema,
+    ) -> Result<CompaniesPriceData, PriceDataError> {
+        let mut companies = CompaniesPriceData::new();
+
+        for exchange in self.exchanges.iter() {
+            companies.append(gather_companies(
+                vec!["all".to_string()],
+                exchange.clone(),
+                schema_for(exchange),
+            )?);
+        }
+        for (exchange, tickers) in self.tickers.iter() {
+            companies.append(gather_companies(
+                tickers.clone(),
+                exchange.clone(),
+                schema_for(exchange),
+            )?);
+        }
+
+        let exclude: HashSet<&str> = self
+            .exclude
+            .iter()
+            .map(|exclude| exclude.as_str())
+            .collect();
+        let mut seen: HashSet<(String, String)> = HashSet::new();
+        let mut universe = CompaniesPriceData::new();
+        for company in companies.companies {
+            let key = format!("{}/{}", company.exchange, company.symbol);
+            let excluded =
+                exclude.contains(company.symbol.as_str()) || exclude.contains(key.as_str());
+            // a company listed both explicitly and through its exchange is only loaded once.
+            if excluded || !seen.insert((company.exchange.clone(), company.symbol.clone())) {
+                continue;
+            }
+            universe.push(company);
+        }
+
+        Ok(universe)
+    }
+}
+
+pub fn gather_companies(
+    data: Vec<String>,
+    exchange: String,
+    schema: CsvSchema,
+) -> Result<CompaniesPriceData, PriceDataError> {
     let mut companies = CompaniesPriceData::new();
     for symbol in data {
         match symbol.as_str() {
             "all" => {
                 companies.flush();
-                fs::read_dir(format!("stock_market_data/{exchange}/csv"))
-                    .unwrap()
-                    .for_each(|entry| {
-                        let entry = entry.unwrap();
-                        let path = entry.path();
-                        let symbol = path.file_stem().unwrap().to_str().unwrap();
-                        companies.push(CompanyPriceData::new(symbol.to_string(), exchange.clone()));
-                    });
+                let path = format!("stock_market_data/{exchange}/csv");
+                let missing_exchange = |source| PriceDataError::MissingExchange {
+                    path: path.clone(),
+                    source,
+                };
+                for entry in fs::read_dir(&path).map_err(missing_exchange)? {
+                    let path = entry.map_err(missing_exchange)?.path();
+                    let symbol =
+                        path.file_stem()
+                            .and_then(|stem| stem.to_str())
+                            .ok_or_else(|| PriceDataError::InvalidFileName {
+                                path: path.display().to_string(),
+                            })?;
+                    companies.push(CompanyPriceData::new(
+                        symbol.to_string(),
+                        exchange.clone(),
+                        schema.clone(),
+                    ));
+                }
                 break;
             }
             _ => {
-                companies.push(CompanyPriceData::new(symbol.to_string(), exchange.clone()));
+                companies.push(CompanyPriceData::new(
+                    symbol.to_string(),
+                    exchange.clone(),
+                    schema.clone(),
+                ));
+            }
+        }
+    }
+    return Ok(companies);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(json: serde_json::Value) -> Result<DataConfig, serde_json::Error> {
+        let mut value = serde_json::json!({
+            "dates": {
+                "train_start": "2015-01-01",
+                "train_end": "2017-01-01",
+                "valid_start": "2017-01-01",
+                "valid_end": "2018-01-01"
             }
+        });
+        for (key, change) in json.as_object().unwrap() {
+            value[key] = change.clone();
         }
+        serde_json::from_value(value)
+    }
+
+    fn symbols(companies: &CompaniesPriceData) -> Vec<String> {
+        companies
+            .iter()
+            .map(|company| format!("{}/{}", company.exchange, company.symbol))
+            .collect()
+    }
+
+    #[test]
+    fn legacy_lists_train_and_validate_on_the_same_companies() {
+        let config = config(serde_json::json!({ "sp500": ["AAPL", "BA"] })).unwrap();
+
+        assert_eq!(
+            symbols(&config.train_companies),
+            vec!["sp500/AAPL", "sp500/BA"]
+        );
+        assert_eq!(
+            symbols(&config.validate_companies),
+            symbols(&config.train_companies)
+        );
+    }
+
+    #[test]
+    fn universes_and_legacy_lists_cannot_be_combined() {
+        let result = config(serde_json::json!({
+            "sp500": ["AAPL"],
+            "universes": {
+                "train": { "tickers": { "sp500": ["BA"] } },
+                "validate": { "tickers": { "sp500": ["CAT"] } }
+            }
+        }));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn universes_are_deduplicated_and_excluded() {
+        let config = config(serde_json::json!({
+            "universes": {
+                "train": {
+                    "tickers": { "nyse": ["BA", "GE"], "sp500": ["AAPL", "BA", "AAPL", "CAT"] },
+                    "exclude": ["CAT", "nyse/GE"]
+                },
+                "validate": { "tickers": { "sp500": ["CAT"] } }
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            symbols(&config.train_companies),
+            vec!["nyse/BA", "sp500/AAPL", "sp500/BA"]
+        );
+        assert_eq!(symbols(&config.validate_companies), vec!["sp500/CAT"]);
+    }
+
+    #[test]
+    fn environment_overrides_nested_keys() {
+        let mut value = serde_json::json!({ "dates": { "train_start": "2015-01-01" } });
+        let vars = vec![
+            (
+                "ML_STOCK__DATES__TRAIN_START".to_string(),
+                "2005-01-01".to_string(),
+            ),
+            (
+                "ML_STOCK__EXPERIMENT__WINDOW_SIZE".to_string(),
+                "64".to_string(),
+            ),
+            ("OTHER".to_string(), "1".to_string()),
+        ];
+        apply_env_overrides(&mut value, vars.into_iter()).unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "dates": { "train_start": "2005-01-01" },
+                "experiment": { "window_size": 64 }
+            })
+        );
+    }
+
+    #[test]
+    fn misspelled_keys_are_rejected() {
+        let err = config(serde_json::json!({ "sp5000": ["AAPL"] })).unwrap_err();
+        assert!(err.to_string().contains("unknown field `sp5000`"), "{err}");
+
+        let err = config(serde_json::json!({ "experiment": { "windw_size": 20 } })).unwrap_err();
+        assert!(
+            err.to_string().contains("unknown field `windw_size`"),
+            "{err}"
+        );
+
+        let err = config(serde_json::json!({ "experiment": { "walk_forward": { "step": 5 } } }))
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown field `step`"), "{err}");
     }
-    return companies;
 }
@@ -0,0 +1,64 @@
+use std::{error::Error, fmt, io};
+
+#[derive(Debug)]
+pub enum PriceDataError {
+    /// The csv file for a symbol could not be opened, usually because the symbol is misspelled in the config.
+    MissingFile { path: String, source: io::Error },
+    /// The csv folder of an exchange could not be listed, usually because the exchange is misspelled in the config.
+    MissingExchange { path: String, source: io::Error },
+    /// A file in an exchange folder has no name that can be read as a symbol.
+    InvalidFileName { path: String },
+    /// The csv reader failed for a reason other than a malformed row length.
+    Csv { symbol: String, source: csv::Error },
+    /// The header row does not contain a column required by the csv schema.
+    MissingColumn { symbol: String, message: String },
+    /// A field could not be parsed into the expected type.
+    Parse {
+        symbol: String,
+        record: usize,
+        field: &'static str,
+        value: String,
+    },
+}
+
+impl fmt::Display for PriceDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PriceDataError::MissingFile { path, source } => {
+                write!(f, "error opening file: {path}, error: {source}")
+            }
+            PriceDataError::MissingExchange { path, source } => {
+                write!(f, "error reading exchange folder: {path}, error: {source}")
+            }
+            PriceDataError::InvalidFileName { path } => {
+                write!(f, "cannot read a symbol from the file name: {path}")
+            }
+            PriceDataError::Csv { symbol, source } => {
+                write!(f, "error reading csv, SYMBOL: {symbol}, error: {source}")
+            }
+            PriceDataError::MissingColumn { symbol, message } => {
+                write!(f, "{message}, SYMBOL: {symbol}")
+            }
+            PriceDataError::Parse {
+                symbol,
+                record,
+                field,
+                value,
+            } => write!(
+                f,
+                "could not parse {field} from {value:?} in record {record}, SYMBOL: {symbol}"
+            ),
+        }
+    }
+}
+
+impl Error for PriceDataError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PriceDataError::MissingFile { source, .. } => Some(source),
+            PriceDataError::MissingExchange { source, .. } => Some(source),
+            PriceDataError::Csv { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
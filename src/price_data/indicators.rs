@@ -0,0 +1,400 @@
+use std::fmt;
+
+use super::PriceDataItem;
+
+/// A technical indicator computed over a company's price data.
+/// Every value only depends on its own row and the rows before it, rows without enough history are NaN.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Indicator {
+    /// Simple moving average of the close.
+    Sma(usize),
+    /// Exponential moving average of the close.
+    Ema(usize),
+    /// Relative strength index with Wilder's smoothing, between 0 and 100.
+    Rsi(usize),
+    /// The fast EMA of the close minus the slow EMA.
+    Macd { fast: usize, slow: usize },
+    /// The EMA of the MACD line.
+    MacdSignal {
+        fast: usize,
+        slow: usize,
+        signal: usize,
+    },
+    /// The distance between the upper and lower Bollinger band, two standard deviations each, over the SMA.
+    BollingerWidth(usize),
+    /// Average true range with Wilder's smoothing.
+    Atr(usize),
+    /// On balance volume, a running total so only its changes are meaningful.
+    Obv,
+    /// Standard deviation of the daily log returns.
+    Volatility(usize),
+}
+
+// Exponentially smoothed indicators never fully forget their first value, they are given this many periods of
+// history before their first value so that a series started later gives the same values.
+const WARM_UP_PERIODS: usize = 4;
+
+impl Indicator {
+    /// Parses an indicator name such as `sma_20`, `rsi_14`, `macd`, `macd_12_26`, `macd_signal_12_26_9` or `obv`.
+    pub fn from_name(name: &str) -> Option<Indicator> {
+        let (kind, params) = match name.find(|c: char| c.is_ascii_digit()) {
+            Some(idx) => (name[..idx].trim_end_matches('_'), &name[idx..]),
+            None => (name, ""),
+        };
+        let params: Vec<usize> = match params {
+            "" => Vec::new(),
+            params => params
+                .split('_')
+                .map(|param| param.parse().ok().filter(|period| *period > 0))
+                .collect::<Option<_>>()?,
+        };
+
+        let indicator = match (kind, params.as_slice()) {
+            ("sma", [period]) => Indicator::Sma(*period),
+            ("ema", [period]) => Indicator::Ema(*period),
+            ("rsi", [period]) => Indicator::Rsi(*period),
+            ("macd", []) => Indicator::Macd { fast: 12, slow: 26 },
+            ("macd", [fast, slow]) if fast < slow => Indicator::Macd {
+                fast: *fast,
+                slow: *slow,
+            },
+            ("macd_signal", []) => Indicator::MacdSignal {
+                fast: 12,
+                slow: 26,
+                signal: 9,
+            },
+            ("macd_signal", [fast, slow, signal]) if fast < slow => Indicator::MacdSignal {
+                fast: *fast,
+                slow: *slow,
+                signal: *signal,
+            },
+            ("bollinger_width", [period]) if *period > 1 => Indicator::BollingerWidth(*period),
+            ("atr", [period]) => Indicator::Atr(*period),
+            ("obv", []) => Indicator::Obv,
+            ("volatility", [period]) if *period > 1 => Indicator::Volatility(*period),
+            _ => return None,
+        };
+
+        Some(indicator)
+    }
+
+    /// The index of the first row that has a value.
+    pub fn lookback(&self) -> usize {
+        match *self {
+            Indicator::Sma(period) | Indicator::BollingerWidth(period) => period - 1,
+            Indicator::Ema(period) => WARM_UP_PERIODS * period,
+            Indicator::Rsi(period) | Indicator::Atr(period) => WARM_UP_PERIODS * period + 1,
+            Indicator::Macd { slow, .. } => WARM_UP_PERIODS * slow,
+            Indicator::MacdSignal { slow, signal, .. } => WARM_UP_PERIODS * (slow + signal),
+            Indicator::Obv => 0,
+            Indicator::Volatility(period) => period,
+        }
+    }
+
+    pub fn compute(&self, price_data: &[PriceDataItem]) -> Vec<f64> {
+        let close: Vec<f64> = price_data.iter().map(|item| item.close).collect();
+
+        let mut values = match *self {
+            Indicator::Sma(period) => sma(&close, period),
+            Indicator::Ema(period) => ema(&close, period),
+            Indicator::Rsi(period) => rsi(&close, period),
+            Indicator::Macd { fast, slow } => macd(&close, fast, slow),
+            Indicator::MacdSignal { fast, slow, signal } => ema(&macd(&close, fast, slow), signal),
+            Indicator::BollingerWidth(period) => sma(&close, period)
+                .iter()
+                .zip(rolling_std(&close, period))
+                .map(|(mean, std)| 4.0 * std / mean)
+                .collect(),
+            Indicator::Atr(period) => atr(price_data, period),
+            Indicator::Obv => obv(price_data),
+            Indicator::Volatility(period) => rolling_std(&log_returns(&close), period),
+        };
+
+        for value in values.iter_mut().take(self.lookback()) {
+            *value = f64::NAN;
+        }
+        values
+    }
+}
+
+impl fmt::Display for Indicator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Indicator::Sma(period) => write!(f, "sma_{period}"),
+            Indicator::Ema(period) => write!(f, "ema_{period}"),
+            Indicator::Rsi(period) => write!(f, "rsi_{period}"),
+            Indicator::Macd { fast, slow } => write!(f, "macd_{fast}_{slow}"),
+            Indicator::MacdSignal { fast, slow, signal } => {
+                write!(f, "macd_signal_{fast}_{slow}_{signal}")
+            }
+            Indicator::BollingerWidth(period) => write!(f, "bollinger_width_{period}"),
+            Indicator::Atr(period) => write!(f, "atr_{period}"),
+            Indicator::Obv => write!(f, "obv"),
+            Indicator::Volatility(period) => write!(f, "volatility_{period}"),
+        }
+    }
+}
+
+fn sma(values: &[f64], period: usize) -> Vec<f64> {
+    let mut out = vec![f64::NAN; values.len()];
+    for idx in (period - 1)..values.len() {
+        out[idx] = values[idx + 1 - period..=idx].iter().sum::<f64>() / period as f64;
+    }
+    out
+}
+
+// Starts from the first value, `lookback` hides the rows where the starting point still matters.
+// NaN inputs, such as days marked as missing, are skipped and carry the previous average forward.
+fn ema(values: &[f64], period: usize) -> Vec<f64> {
+    smoothed(values, 2.0 / (period as f64 + 1.0))
+}
+
+// Wilder's moving average, an EMA with a smoothing factor of 1 / period.
+fn wilder(values: &[f64], period: usize) -> Vec<f64> {
+    smoothed(values, 1.0 / period as f64)
+}
+
+fn smoothed(values: &[f64], alpha: f64) -> Vec<f64> {
+    let mut out = Vec::with_capacity(values.len());
+    let mut average = f64::NAN;
+    for value in values.iter() {
+        if value.is_finite() {
+            average = match average.is_finite() {
+                true => average + alpha * (value - average),
+                false => *value,
+            };
+        }
+        out.push(average);
+    }
+    out
+}
+
+fn rolling_std(values: &[f64], period: usize) -> Vec<f64> {
+    let mut out = vec![f64::NAN; values.len()];
+    for idx in (period - 1)..values.len() {
+        let window = &values[idx + 1 - period..=idx];
+        let mean = window.iter().sum::<f64>() / period as f64;
+        let variance = window.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / period as f64;
+        out[idx] = variance.sqrt();
+    }
+    out
+}
+
+fn log_returns(close: &[f64]) -> Vec<f64> {
+    std::iter::once(f64::NAN)
+        .chain(close.windows(2).map(|pair| (pair[1] / pair[0]).ln()))
+        .take(close.len())
+        .collect()
+}
+
+fn rsi(close: &[f64], period: usize) -> Vec<f64> {
+    let changes: Vec<f64> = std::iter::once(f64::NAN)
+        .chain(close.windows(2).map(|pair| pair[1] - pair[0]))
+        .take(close.len())
+        .collect();
+    let gains: Vec<f64> = changes.iter().map(|change| change.max(0.0)).collect();
+    let losses: Vec<f64> = changes.iter().map(|change| (-change).max(0.0)).collect();
+
+    wilder(&gains, period)
+        .iter()
+        .zip(wilder(&losses, period))
+        .map(|(gain, loss)| {
+            // a flat series is neither overbought nor oversold.
+            if gain + loss == 0.0 {
+                return 50.0;
+            }
+            100.0 * gain / (gain + loss)
+        })
+        .collect()
+}
+
+fn macd(close: &[f64], fast: usize, slow: usize) -> Vec<f64> {
+    ema(close, fast)
+        .iter()
+        .zip(ema(close, slow))
+        .map(|(fast, slow)| fast - slow)
+        .collect()
+}
+
+fn atr(price_data: &[PriceDataItem], period: usize) -> Vec<f64> {
+    let true_range: Vec<f64> = price_data
+        .iter()
+        .enumerate()
+        .map(|(idx, item)| match idx {
+            0 => item.high - item.low,
+            _ => {
+                let prev_close = price_data[idx - 1].close;
+                (item.high - item.low)
+                    .max((item.high - prev_close).abs())
+                    .max((item.low - prev_close).abs())
+            }
+        })
+        .collect();
+
+    wilder(&true_range, period)
+}
+
+fn obv(price_data: &[PriceDataItem]) -> Vec<f64> {
+    let mut out = Vec::with_capacity(price_data.len());
+    let mut total = 0.0;
+    for (idx, item) in price_data.iter().enumerate() {
+        if idx > 0 {
+            let prev_close = price_data[idx - 1].close;
+            if item.close > prev_close {
+                total += item.volume as f64;
+            } else if item.close < prev_close {
+                total -= item.volume as f64;
+            }
+        }
+        out.push(total);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price_data::testing;
+    use chrono::{Days, NaiveDate};
+
+    fn price_data(closes: &[f64]) -> Vec<PriceDataItem> {
+        let start = NaiveDate::from_ymd_opt(2001, 1, 1).unwrap();
+        closes
+            .iter()
+            .enumerate()
+            .map(|(idx, close)| PriceDataItem {
+                high: close + 1.0,
+                low: close - 1.0,
+                ..testing::item(start + Days::new(idx as u64), *close, 10 * (idx as i64 + 1))
+            })
+            .collect()
+    }
+
+    fn compute(name: &str, closes: &[f64]) -> Vec<f64> {
+        Indicator::from_name(name)
+            .unwrap()
+            .compute(&price_data(closes))
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                (actual.is_nan() && expected.is_nan()) || (actual - expected).abs() < 1e-9,
+                "{actual:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_and_prints_names() {
+        for name in [
+            "sma_20",
+            "ema_12",
+            "rsi_14",
+            "macd_12_26",
+            "macd_signal_12_26_9",
+            "bollinger_width_20",
+            "atr_14",
+            "obv",
+            "volatility_20",
+        ] {
+            assert_eq!(Indicator::from_name(name).unwrap().to_string(), name);
+        }
+        assert_eq!(
+            Indicator::from_name("macd"),
+            Some(Indicator::Macd { fast: 12, slow: 26 })
+        );
+        for name in [
+            "sma",
+            "sma_0",
+            "macd_26_12",
+            "volatility_1",
+            "rsi_14_2",
+            "foo_3",
+        ] {
+            assert_eq!(Indicator::from_name(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn sma_averages_the_last_period_closes() {
+        let nan = f64::NAN;
+        assert_close(
+            &compute("sma_3", &[1.0, 2.0, 3.0, 4.0, 8.0]),
+            &[nan, nan, 2.0, 3.0, 5.0],
+        );
+    }
+
+    #[test]
+    fn rows_before_the_lookback_have_no_value() {
+        let closes: Vec<f64> = (0..200).map(|idx| 100.0 + (idx as f64).sin()).collect();
+        for name in [
+            "ema_5",
+            "rsi_14",
+            "macd_signal_12_26_9",
+            "atr_14",
+            "volatility_20",
+        ] {
+            let indicator = Indicator::from_name(name).unwrap();
+            let values = indicator.compute(&price_data(&closes));
+
+            let first = values.iter().position(|value| value.is_finite());
+            assert_eq!(first, Some(indicator.lookback()), "{name}");
+        }
+    }
+
+    #[test]
+    fn values_do_not_depend_on_later_rows() {
+        let closes: Vec<f64> = (0..120)
+            .map(|idx| 100.0 + (idx as f64 / 3.0).cos())
+            .collect();
+        for name in [
+            "sma_10",
+            "ema_10",
+            "rsi_14",
+            "macd",
+            "bollinger_width_20",
+            "obv",
+        ] {
+            let full = compute(name, &closes);
+            let prefix = compute(name, &closes[..80]);
+            assert_close(&full[..80], &prefix);
+        }
+    }
+
+    #[test]
+    fn rsi_of_trending_and_flat_series() {
+        let rising: Vec<f64> = (0..40).map(|idx| idx as f64).collect();
+        let falling: Vec<f64> = rising.iter().rev().cloned().collect();
+
+        assert_eq!(compute("rsi_5", &rising).last(), Some(&100.0));
+        assert_eq!(compute("rsi_5", &falling).last(), Some(&0.0));
+        assert_eq!(compute("rsi_5", &[10.0; 40]).last(), Some(&50.0));
+    }
+
+    #[test]
+    fn ema_of_a_constant_is_the_constant() {
+        let values = compute("ema_5", &[7.0; 30]);
+        assert!(values[20..].iter().all(|value| *value == 7.0));
+    }
+
+    #[test]
+    fn atr_and_bands_of_a_steady_series() {
+        // the high to low range is always 2, a steady series has no spread around its average.
+        assert_eq!(compute("atr_3", &[10.0; 20]).last(), Some(&2.0));
+        assert_eq!(compute("bollinger_width_5", &[10.0; 20]).last(), Some(&0.0));
+
+        let growth: Vec<f64> = (0..30).map(|idx| 1.01f64.powi(idx)).collect();
+        assert!(compute("volatility_5", &growth).last().unwrap().abs() < 1e-12);
+    }
+
+    #[test]
+    fn obv_adds_volume_on_up_days_and_subtracts_it_on_down_days() {
+        // volumes are 10, 20, 30, 40 and 50.
+        assert_close(
+            &compute("obv", &[10.0, 11.0, 10.0, 10.0, 12.0]),
+            &[0.0, 20.0, -10.0, -10.0, 40.0],
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt;
+
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Deserialize;
+
+use super::{
+    adjust::PriceAdjustment,
+    error::PriceDataError,
+    validate::{QualityReport, ValidationConfig},
+    Company,
+};
+
+/// Decides what happens when the price data of a single symbol cannot be loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadPolicy {
+    /// Stop loading and return the first error.
+    FailFast,
+    /// Drop the symbol from the universe, record why, and keep loading the rest.
+    #[default]
+    SkipAndContinue,
+}
+
+/// Everything that decides how a universe of companies is loaded.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoadOptions {
+    pub policy: LoadPolicy,
+    pub adjustment: PriceAdjustment,
+    pub cache: bool,
+    pub validation: ValidationConfig,
+}
+
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    pub loaded: Vec<Company>,
+    pub skipped: Vec<(Company, PriceDataError)>,
+    // The number of malformed csv rows skipped for every company that had any.
+    pub malformed_rows: Vec<(Company, usize)>,
+    pub quality: Vec<QualityReport>,
+}
+
+impl LoadReport {
+    pub fn new() -> LoadReport {
+        LoadReport::default()
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Loaded {loaded} companies, skipped {skipped}",
+            loaded = self.loaded.len(),
+            skipped = self.skipped.len()
+        )?;
+        for (company, err) in self.skipped.iter() {
+            writeln!(
+                f,
+                "  SKIPPED {exchange}/{symbol}: {err}",
+                exchange = company.exchange,
+                symbol = company.symbol
+            )?;
+        }
+
+        if !self.malformed_rows.is_empty() {
+            let total: usize = self.malformed_rows.iter().map(|(_, rows)| rows).sum();
+            writeln!(
+                f,
+                "Skipped {total} malformed csv rows in {} companies",
+                self.malformed_rows.len()
+            )?;
+            for (company, rows) in self.malformed_rows.iter() {
+                writeln!(
+                    f,
+                    "  {exchange}/{symbol}: {rows} rows",
+                    exchange = company.exchange,
+                    symbol = company.symbol
+                )?;
+            }
+        }
+
+        let issues: Vec<&QualityReport> = self
+            .quality
+            .iter()
+            .filter(|quality| !quality.is_clean())
+            .collect();
+        if !issues.is_empty() {
+            writeln!(f, "Data quality issues in {} companies", issues.len())?;
+            for quality in issues {
+                writeln!(f, "  {quality}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn progress_bar(len: usize, message: &'static str) -> ProgressBar {
+    let style = ProgressStyle::with_template("{msg} [{bar:40}] {pos}/{len} companies ({eta})")
+        .unwrap()
+        .progress_chars("=> ");
+    ProgressBar::new(len as u64)
+        .with_style(style)
+        .with_message(message)
+}
@@ -1,25 +1,45 @@
+mod adjust;
+mod align;
+mod cache;
+mod calendar;
 mod config;
+mod error;
+mod indicators;
+mod load;
 mod parse;
+mod schema;
+#[cfg(test)]
+pub(crate) mod testing;
+mod validate;
+pub use adjust::PriceAdjustment;
+pub use align::GapFill;
+pub use cache::build_exchange_cache;
+pub use calendar::{CalendarKind, TradingCalendar};
 pub use config::DataConfig;
+pub use error::PriceDataError;
+pub use indicators::Indicator;
+pub use load::{LoadOptions, LoadPolicy, LoadReport};
+pub use schema::CsvSchema;
+pub use validate::{QualityReport, ValidationConfig};
 
 use std::{
     fs::File,
     hash::{Hash, Hasher},
 };
 
+use cache::PriceDataCache;
 use chrono::NaiveDate;
-
-use crate::ml_model::CHUNK_SIZE;
+use rayon::prelude::*;
 
 #[derive(Debug, Clone, Copy)]
 pub struct PriceDataItem {
     pub date: NaiveDate,
-    // pub open: f64,
-    // pub high: f64,
-    // pub low: f64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
     pub close: f64,
-    // pub volume: i64,
-    // pub adjusted_close: f64,
+    pub volume: i64,
+    pub adjusted_close: f64,
 }
 
 impl Hash for PriceDataItem {
@@ -44,6 +64,7 @@ impl PartialEq for Company {
 pub struct CompanyPriceData {
     pub symbol: String,
     pub exchange: String,
+    pub schema: CsvSchema,
     pub price_data: Vec<PriceDataItem>,
 }
 
@@ -54,17 +75,70 @@ impl PartialEq for CompanyPriceData {
 }
 
 impl CompanyPriceData {
-    pub fn new(symbol: String, exchange: String) -> CompanyPriceData {
+    pub fn new(symbol: String, exchange: String, schema: CsvSchema) -> CompanyPriceData {
         CompanyPriceData {
             symbol: symbol,
             exchange: exchange,
+            schema: schema,
             price_data: Vec::new(),
         }
     }
 
-    pub fn refresh_data(&mut self, start: NaiveDate, end: NaiveDate) {
-        self.price_data =
-            parse::fetch_company_price_data(self.get_file(), start, end, self.symbol.clone());
+    /// Reads the price data between `start` and `end`, returning the number of malformed csv rows that were skipped.
+    /// With `cache` enabled the parsed csv is stored on disk and reused until the csv file is modified.
+    pub fn refresh_data(
+        &mut self,
+        start: NaiveDate,
+        end: NaiveDate,
+        cache: bool,
+    ) -> Result<usize, PriceDataError> {
+        let file = self.get_file()?;
+        let modified = file
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        let modified = match modified {
+            Some(modified) if cache => modified,
+            // without a modification time there is no way to tell if a cache entry is stale.
+            _ => {
+                let (price_data, skipped) = parse::fetch_company_price_data(
+                    file,
+                    start,
+                    end,
+                    self.symbol.clone(),
+                    &self.schema,
+                )?;
+                self.price_data = price_data;
+                return Ok(skipped);
+            }
+        };
+
+        let path = self.file_path();
+        let cache_path = cache::cache_path(&self.exchange, &self.symbol);
+
+        if let Some(cached) = PriceDataCache::read(&cache_path) {
+            if cached.is_valid_for(&path, modified, &self.schema) {
+                self.price_data = cached.price_data(start, end);
+                return Ok(cached.skipped_rows());
+            }
+        }
+
+        // the cache holds the whole file so that it can serve any date range.
+        let (price_data, skipped) = parse::fetch_company_price_data(
+            file,
+            NaiveDate::MIN,
+            NaiveDate::MAX,
+            self.symbol.clone(),
+            &self.schema,
+        )?;
+        let cached = PriceDataCache::new(path, modified, self.schema.clone(), &price_data, skipped);
+        if let Err(err) = cached.write(&cache_path) {
+            println!("WARNING: could not write price data cache {cache_path}: {err}");
+        }
+
+        self.price_data = cached.price_data(start, end);
+        Ok(skipped)
     }
 
     // This function is a little bit awkward because our argument is the current date, while the function utilizes the next day.
@@ -101,29 +175,25 @@ impl CompanyPriceData {
         }
     }
 
-    fn get_file(&self) -> File {
-        let path = format!(
+    fn file_path(&self) -> String {
+        format!(
             "stock_market_data/{exchange}/csv/{symbol}.csv",
             exchange = self.exchange,
             symbol = self.symbol
-        );
-
-        let file = File::open(path.clone());
-
-        match file {
-            Ok(file) => file,
-            Err(e) => {
-                panic!(
-                    "Error opening file: {path}, error: {error}",
-                    path = path,
-                    error = e
-                );
-            }
-        }
+        )
+    }
+
+    fn get_file(&self) -> Result<File, PriceDataError> {
+        let path = self.file_path();
+
+        File::open(path.clone()).map_err(|source| PriceDataError::MissingFile { path, source })
     }
 }
 
-#[derive(Debug, Clone)]
+// The quality report and the number of skipped csv rows of a company, or why it could not be loaded.
+type LoadResult = Result<(QualityReport, usize), PriceDataError>;
+
+#[derive(Debug, Clone, Default)]
 pub struct CompaniesPriceData {
     pub companies: Vec<CompanyPriceData>,
 }
@@ -150,39 +220,97 @@ impl CompaniesPriceData {
         self.companies.append(&mut other.companies);
     }
 
-    pub fn refresh_data(&mut self, start: NaiveDate, end: NaiveDate) {
-        let total = self.companies.len();
-        for (idx, company) in &mut self.companies.iter_mut().enumerate() {
-            if idx % 10 == 0 {
-                print!("{}[2J", 27 as char);
-                println!("Fetching data... company {idx} of {total}", idx = idx,);
+    /// Loads and adjusts the price data of every company.
+    /// Under `LoadPolicy::SkipAndContinue` companies that fail to load are removed and listed in the report.
+    pub fn refresh_data(
+        &mut self,
+        start: NaiveDate,
+        end: NaiveDate,
+        options: LoadOptions,
+    ) -> Result<LoadReport, PriceDataError> {
+        let mut report = LoadReport::new();
+        let mut loaded = Vec::new();
+
+        let companies: Vec<CompanyPriceData> = self.companies.drain(..).collect();
+        let progress = load::progress_bar(companies.len(), "Fetching data");
+
+        // rows are validated before they are adjusted so that a bad close cannot skew the adjustment factors.
+        let load_company = |mut company: CompanyPriceData| {
+            let result = company
+                .refresh_data(start, end, options.cache)
+                .map(|skipped| (company.validate(&options.validation), skipped))
+                .and_then(|loaded| company.adjust(options.adjustment).map(|_| loaded));
+            progress.inc(1);
+            (company, result)
+        };
+
+        // files are read and parsed across the rayon thread pool, `collect` keeps the config order.
+        // under `FailFast` the collect into a `Result` stops handing out companies after the first error.
+        let results: Vec<(CompanyPriceData, LoadResult)> = match options.policy {
+            LoadPolicy::FailFast => {
+                let results: Result<Vec<_>, PriceDataError> = companies
+                    .into_par_iter()
+                    .map(load_company)
+                    .map(|(company, result)| result.map(|loaded| (company, Ok(loaded))))
+                    .collect();
+                match results {
+                    Ok(results) => results,
+                    Err(err) => {
+                        progress.abandon_with_message("Company data fetch failed");
+                        return Err(err);
+                    }
+                }
             }
+            LoadPolicy::SkipAndContinue => companies.into_par_iter().map(load_company).collect(),
+        };
 
-            company.refresh_data(start, end);
+        progress.finish_with_message("Company data fetch complete");
+
+        for (company, result) in results {
+            match result {
+                Ok((quality, skipped)) => {
+                    if skipped > 0 {
+                        report.malformed_rows.push((company.company(), skipped));
+                    }
+                    report.loaded.push(company.company());
+                    report.quality.push(quality);
+                    loaded.push(company);
+                }
+                Err(err) => report.skipped.push((company.company(), err)),
+            }
         }
-        print!("{}[2J", 27 as char);
-        println!("Company data fetch complete");
+        self.companies = loaded;
+
+        Ok(report)
     }
 
     pub fn iter(&self) -> std::slice::Iter<CompanyPriceData> {
         self.companies.iter()
     }
 
-    pub fn fetch_validate_data_set(&self, curr_date: NaiveDate) -> Vec<SearchableCompany> {
-        let set = self.fetch_last_n_days(curr_date).clone();
+    pub fn fetch_validate_data_set(
+        &self,
+        curr_date: NaiveDate,
+        window_size: usize,
+    ) -> Vec<SearchableCompany> {
+        let set = self.fetch_last_n_days(curr_date, window_size).clone();
         // this is a crutch to get the output to intigrate with the .batch() and .step() functions later on.
         return set;
     }
 
-    fn fetch_last_n_days(&self, curr_date: NaiveDate) -> Vec<SearchableCompany> {
+    fn fetch_last_n_days(
+        &self,
+        curr_date: NaiveDate,
+        window_size: usize,
+    ) -> Vec<SearchableCompany> {
         let mut items = Vec::new();
         for company in self.companies.iter() {
-            let data = company.to_owned().fetch_last_n_days(curr_date, CHUNK_SIZE);
+            let data = company.to_owned().fetch_last_n_days(curr_date, window_size);
 
             match data {
                 None => continue,
                 Some(price_data) => {
-                    if price_data.len() < CHUNK_SIZE {
+                    if price_data.len() < window_size {
                         continue;
                     }
                     items.push(SearchableCompany::new(company.clone(), price_data))
@@ -1,89 +1,95 @@
-use std::fs::File;
+use std::{io::Read, str::FromStr};
 
 use chrono::NaiveDate;
+use csv::StringRecord;
 
-use super::PriceDataItem;
+use super::{error::PriceDataError, schema::CsvSchema, PriceDataItem};
 
+/// Reads the rows between `start` and `end`, and counts the malformed rows of the whole file that were skipped.
 pub fn fetch_company_price_data(
-    file: File,
+    file: impl Read,
     start: NaiveDate,
     end: NaiveDate,
     symbol: String,
-) -> Vec<PriceDataItem> {
+    schema: &CsvSchema,
+) -> Result<(Vec<PriceDataItem>, usize), PriceDataError> {
     let mut rdr = csv::Reader::from_reader(file);
     let mut records: Vec<PriceDataItem> = Vec::new();
+    let mut skipped = 0;
+
+    let headers = rdr
+        .headers()
+        .map_err(|source| PriceDataError::Csv {
+            symbol: symbol.clone(),
+            source,
+        })?
+        .clone();
+    let columns = schema
+        .resolve(&headers)
+        .map_err(|message| PriceDataError::MissingColumn {
+            symbol: symbol.clone(),
+            message,
+        })?;
 
     for (num, result) in rdr.records().enumerate() {
-        // Some records in the data have malformed lengths.
-        // Because we cannot know the fields that these records belong to are for,
-        // and we cannot know the correct values for these fields, we skip these records.
-        if result.is_err() {
-            let err = result.err().unwrap();
-            match err.kind() {
+        let record = match result {
+            Ok(record) => record,
+            Err(err) => match err.kind() {
+                // Some records in the data have malformed lengths.
+                // Because we cannot know the fields that these records belong to are for,
+                // and we cannot know the correct values for these fields, we skip these records.
                 csv::ErrorKind::UnequalLengths {
                     pos: _pos,
                     expected_len: _expected_len,
                     len: _len,
                 } => {
-                    println!(
-                        "SKIPPING RECORD, error in price data csv record row:{num} {:?}, SYMBOL: {symbol}",
-                        err
-                    );
+                    skipped += 1;
                     continue;
                 }
                 _ => {
-                    panic!("{err}");
+                    return Err(PriceDataError::Csv {
+                        symbol,
+                        source: err,
+                    });
                 }
-            }
-        }
-        let record =
-            result.expect(format!("error in price data csv record, SYMBOL: {symbol}",).as_str());
+            },
+        };
 
         // when the stock did not trade on this day, continue to the next record
-        if record[1].is_empty() {
+        if record[columns.close].is_empty() {
             continue;
         }
 
-        // This handles malformed dates in the csv. As these do not conform to the standard date format of the data,
-        // therefore the date string cannot be reliably parsed and the record is skipped.
-        if record[0].len() != 10 {
-            println!("SKIPPING RECORD, malformed date, SYMBOL: {symbol}",);
-            continue;
-        }
+        let field = FieldParser {
+            record: &record,
+            num,
+            symbol: &symbol,
+        };
 
-        // let volume: i64 = record[3]
-        //     .split(".")
-        //     .next()
-        //     .unwrap()
-        //     .parse()
-        //     .expect(format!("Could not parse volume: {:?}", record).as_str());
+        // This handles malformed dates in the csv. As these do not conform to the date format of the schema,
+        // the date cannot be reliably read and the record is skipped.
+        let date = match NaiveDate::parse_from_str(&record[columns.date], &schema.date_format) {
+            Ok(date) => date,
+            Err(_) => {
+                skipped += 1;
+                continue;
+            }
+        };
+
+        let close = field.parse(columns.close, "close")?;
 
-        // This handles the edge case where the volume is a whole number but the csv file has a decimal value of 0.
-        // Sometimes rust cannot safely parse this value, and errors out.
-        // We know that all volumes are whole numbers, so we can safely parse the volume as an integer by dropping the decimal.
         let record = PriceDataItem {
-            date: NaiveDate::parse_from_str(&record[0], "%d-%m-%Y")
-                .expect(format!("Could not parse date: {:?}, SYMBOL: {symbol}", record).as_str()),
-            // low: record[1]
-            //     .parse()
-            //     .expect(format!("Could not parse low: {:?} SYMBOL: {symbol}", record).as_str()),
-            // open: record[2]
-            //     .parse()
-            //     .expect(format!("Could not parse open: {:?} SYMBOL: {symbol}", record).as_str()),
-            // volume: volume,
-            // high: record[4]
-            //     .parse()
-            //     .expect(format!("Could not parse high: {:?}  SYMBOL: {symbol}", record).as_str()),
-            close: record[5]
-                .parse()
-                .expect(format!("Could not parse close: {:?}  SYMBOL: {symbol}", record).as_str()),
-            // adjusted_close: record[6].parse().expect(
-            //     format!(
-            //         "Could not parse adjusted_close: {:?}  SYMBOL: {symbol}",
-            //         record
-            //     )
-            //     .as_str(),
-            // ),
+            date,
+            low: field.parse(columns.low, "low")?,
+            open: field.parse(columns.open, "open")?,
+            volume: parse_volume(&record[columns.volume])
+                .ok_or_else(|| field.error(columns.volume, "volume"))?,
+            high: field.parse(columns.high, "high")?,
+            close,
+            adjusted_close: match columns.adjusted_close {
+                Some(idx) => field.parse(idx, "adjusted_close")?,
+                None => close,
+            },
         };
 
         if record.date < start || record.date > end {
@@ -92,5 +98,174 @@ pub fn fetch_company_price_data(
 
         records.push(record);
     }
-    return records;
+    return Ok((records, skipped));
+}
+
+// Keeps the context of the record being parsed so that every field error reports where it came from.
+struct FieldParser<'a> {
+    record: &'a StringRecord,
+    num: usize,
+    symbol: &'a str,
+}
+
+impl FieldParser<'_> {
+    fn parse<T: FromStr>(&self, idx: usize, field: &'static str) -> Result<T, PriceDataError> {
+        self.record[idx].parse().map_err(|_| self.error(idx, field))
+    }
+
+    fn error(&self, idx: usize, field: &'static str) -> PriceDataError {
+        PriceDataError::Parse {
+            symbol: self.symbol.to_string(),
+            record: self.num,
+            field,
+            value: self.record[idx].to_string(),
+        }
+    }
+}
+
+// This handles the edge case where the volume is a whole number but the csv file has a decimal value of 0 (e.g. "1200.0").
+// Rust will not parse such a string as an integer, so we fall back to parsing it as a float.
+// We know that all volumes are whole numbers, so we can safely round the float to an integer.
+fn parse_volume(value: &str) -> Option<i64> {
+    if let Ok(volume) = value.parse::<i64>() {
+        return Some(volume);
+    }
+
+    match value.parse::<f64>() {
+        Ok(volume) if volume.is_finite() => Some(volume.round() as i64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn parse(csv: &str, schema: &CsvSchema) -> Result<Vec<PriceDataItem>, PriceDataError> {
+        fetch_company_price_data(
+            csv.as_bytes(),
+            NaiveDate::MIN,
+            NaiveDate::MAX,
+            "TEST".to_string(),
+            schema,
+        )
+        .map(|(items, _)| items)
+    }
+
+    #[test]
+    fn parses_every_field() {
+        let csv = "Date,Low,Open,Volume,High,Close,Adjusted Close\n\
+                   02-01-2001,9.5,10,1200,11,10.5,5.25\n";
+        let items = parse(csv, &CsvSchema::kaggle()).unwrap();
+
+        assert_eq!(items.len(), 1);
+        let item = items[0];
+        assert_eq!(item.date, date(2001, 1, 2));
+        assert_eq!(item.open, 10.0);
+        assert_eq!(item.high, 11.0);
+        assert_eq!(item.low, 9.5);
+        assert_eq!(item.close, 10.5);
+        assert_eq!(item.volume, 1200);
+        assert_eq!(item.adjusted_close, 5.25);
+    }
+
+    #[test]
+    fn missing_adjusted_close_column_uses_the_close() {
+        let csv = "Date,Open,High,Low,Close,Volume\n2001-01-02,10,11,9,10.5,100\n";
+        let items = parse(csv, &CsvSchema::stooq()).unwrap();
+
+        assert_eq!(items[0].adjusted_close, 10.5);
+    }
+
+    #[test]
+    fn parses_decimal_volumes() {
+        assert_eq!(parse_volume("1200"), Some(1200));
+        assert_eq!(parse_volume("1200.0"), Some(1200));
+        assert_eq!(parse_volume("abc"), None);
+        assert_eq!(parse_volume("NaN"), None);
+    }
+
+    #[test]
+    fn skips_days_without_a_close_and_outside_the_range() {
+        let csv = "Date,Open,High,Low,Close,Volume\n\
+                   2001-01-02,10,11,9,10.5,100\n\
+                   2001-01-03,,,,,\n\
+                   2001-01-04,10,11,9,10.5,100\n\
+                   2001-01-05,10,11,9,10.5,100\n";
+        let items = fetch_company_price_data(
+            csv.as_bytes(),
+            date(2001, 1, 1),
+            date(2001, 1, 4),
+            "TEST".to_string(),
+            &CsvSchema::stooq(),
+        )
+        .unwrap()
+        .0;
+
+        let dates: Vec<NaiveDate> = items.iter().map(|item| item.date).collect();
+        assert_eq!(dates, vec![date(2001, 1, 2), date(2001, 1, 4)]);
+    }
+
+    #[test]
+    fn reports_the_field_that_cannot_be_parsed() {
+        let csv = "Date,Open,High,Low,Close,Volume\n2001-01-02,10,eleven,9,10.5,100\n";
+        let err = parse(csv, &CsvSchema::stooq()).unwrap_err();
+
+        assert!(matches!(
+            err,
+            PriceDataError::Parse {
+                record: 0,
+                field: "high",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parses_variable_width_dates() {
+        let schema = CsvSchema {
+            date_format: "%m/%d/%Y".to_string(),
+            ..CsvSchema::stooq()
+        };
+        let csv = "Date,Open,High,Low,Close,Volume\n\
+                   1/2/2001,10,11,9,10.5,100\n\
+                   12/31/2001,10,11,9,10.5,100\n";
+        let items = parse(csv, &schema).unwrap();
+
+        let dates: Vec<NaiveDate> = items.iter().map(|item| item.date).collect();
+        assert_eq!(dates, vec![date(2001, 1, 2), date(2001, 12, 31)]);
+    }
+
+    #[test]
+    fn skips_and_counts_malformed_rows() {
+        let csv = "Date,Open,High,Low,Close,Volume\n\
+                   2001-01-02,10,11,9,10.5,100\n\
+                   2001-01-0x,10,11,9,10.5,100\n\
+                   2001-01-03,10,11\n\
+                   2001-01-04,10,11,9,10.5,100\n";
+        let (items, skipped) = fetch_company_price_data(
+            csv.as_bytes(),
+            NaiveDate::MIN,
+            NaiveDate::MAX,
+            "TEST".to_string(),
+            &CsvSchema::stooq(),
+        )
+        .unwrap();
+
+        let dates: Vec<NaiveDate> = items.iter().map(|item| item.date).collect();
+        assert_eq!(dates, vec![date(2001, 1, 2), date(2001, 1, 4)]);
+        assert_eq!(skipped, 2);
+    }
+
+    #[test]
+    fn reports_a_missing_column() {
+        let csv = "Date,Open,High,Low,Close\n2001-01-02,10,11,9,10.5\n";
+        let err = parse(csv, &CsvSchema::stooq()).unwrap_err();
+
+        assert!(matches!(err, PriceDataError::MissingColumn { .. }));
+    }
 }
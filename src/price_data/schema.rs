@@ -0,0 +1,188 @@
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+
+/// Describes the layout of a price data csv file.
+/// Columns are looked up by their header name, so the order of the columns in the file does not matter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CsvSchema {
+    pub date: String,
+    pub open: String,
+    pub high: String,
+    pub low: String,
+    pub close: String,
+    pub volume: String,
+    // Not every vendor publishes an adjusted close, when it is missing the close is used instead.
+    #[serde(default)]
+    pub adjusted_close: Option<String>,
+    pub date_format: String,
+}
+
+impl CsvSchema {
+    /// The layout of the kaggle stock market dataset that ships with this repository.
+    pub fn kaggle() -> CsvSchema {
+        CsvSchema {
+            date: "Date".to_string(),
+            open: "Open".to_string(),
+            high: "High".to_string(),
+            low: "Low".to_string(),
+            close: "Close".to_string(),
+            volume: "Volume".to_string(),
+            adjusted_close: Some("Adjusted Close".to_string()),
+            date_format: "%d-%m-%Y".to_string(),
+        }
+    }
+
+    pub fn yahoo() -> CsvSchema {
+        CsvSchema {
+            date: "Date".to_string(),
+            open: "Open".to_string(),
+            high: "High".to_string(),
+            low: "Low".to_string(),
+            close: "Close".to_string(),
+            volume: "Volume".to_string(),
+            adjusted_close: Some("Adj Close".to_string()),
+            date_format: "%Y-%m-%d".to_string(),
+        }
+    }
+
+    pub fn stooq() -> CsvSchema {
+        CsvSchema {
+            date: "Date".to_string(),
+            open: "Open".to_string(),
+            high: "High".to_string(),
+            low: "Low".to_string(),
+            close: "Close".to_string(),
+            volume: "Volume".to_string(),
+            adjusted_close: None,
+            date_format: "%Y-%m-%d".to_string(),
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<CsvSchema> {
+        match name.to_lowercase().as_str() {
+            "kaggle" => Some(CsvSchema::kaggle()),
+            "yahoo" => Some(CsvSchema::yahoo()),
+            "stooq" => Some(CsvSchema::stooq()),
+            _ => None,
+        }
+    }
+
+    /// Finds the position of every configured column in the header row of the csv file.
+    pub fn resolve(&self, headers: &StringRecord) -> Result<ColumnIndices, String> {
+        let find = |name: &str| -> Result<usize, String> {
+            headers
+                .iter()
+                .position(|header| header.trim().eq_ignore_ascii_case(name.trim()))
+                .ok_or(format!("missing column \"{name}\" in header {:?}", headers))
+        };
+
+        let adjusted_close = match &self.adjusted_close {
+            Some(name) => Some(find(name)?),
+            None => None,
+        };
+
+        Ok(ColumnIndices {
+            date: find(&self.date)?,
+            open: find(&self.open)?,
+            high: find(&self.high)?,
+            low: find(&self.low)?,
+            close: find(&self.close)?,
+            volume: find(&self.volume)?,
+            adjusted_close,
+        })
+    }
+}
+
+impl Default for CsvSchema {
+    fn default() -> Self {
+        CsvSchema::kaggle()
+    }
+}
+
+/// The configuration file can either name a preset schema or spell out every column.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum CsvSchemaJson {
+    Preset(String),
+    Custom(CsvSchema),
+}
+
+impl CsvSchemaJson {
+    pub fn into_schema(self) -> Result<CsvSchema, String> {
+        match self {
+            CsvSchemaJson::Preset(name) => {
+                CsvSchema::preset(&name).ok_or(format!("unknown csv schema preset \"{name}\""))
+            }
+            CsvSchemaJson::Custom(schema) => Ok(schema),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ColumnIndices {
+    pub date: usize,
+    pub open: usize,
+    pub high: usize,
+    pub low: usize,
+    pub close: usize,
+    pub volume: usize,
+    pub adjusted_close: Option<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_columns_by_header_name() {
+        let headers = StringRecord::from(vec![
+            "volume",
+            " Close ",
+            "Low",
+            "High",
+            "Open",
+            "Date",
+            "Adj Close",
+        ]);
+        let columns = CsvSchema::yahoo().resolve(&headers).unwrap();
+
+        assert_eq!(columns.date, 5);
+        assert_eq!(columns.open, 4);
+        assert_eq!(columns.high, 3);
+        assert_eq!(columns.low, 2);
+        assert_eq!(columns.close, 1);
+        assert_eq!(columns.volume, 0);
+        assert_eq!(columns.adjusted_close, Some(6));
+    }
+
+    #[test]
+    fn missing_column_is_an_error() {
+        let headers = StringRecord::from(vec!["Date", "Open", "High", "Low", "Close", "Volume"]);
+
+        assert!(CsvSchema::yahoo().resolve(&headers).is_err());
+        assert!(CsvSchema::stooq().resolve(&headers).is_ok());
+    }
+
+    #[test]
+    fn reads_presets_and_custom_schemas() {
+        let preset: CsvSchemaJson = serde_json::from_str("\"Yahoo\"").unwrap();
+        assert_eq!(preset.into_schema(), Ok(CsvSchema::yahoo()));
+
+        let unknown: CsvSchemaJson = serde_json::from_str("\"bloomberg\"").unwrap();
+        assert!(unknown.into_schema().is_err());
+
+        let custom: CsvSchemaJson = serde_json::from_value(serde_json::json!({
+            "date": "day",
+            "open": "o",
+            "high": "h",
+            "low": "l",
+            "close": "c",
+            "volume": "v",
+            "date_format": "%m/%d/%Y"
+        }))
+        .unwrap();
+        let schema = custom.into_schema().unwrap();
+        assert_eq!(schema.adjusted_close, None);
+        assert_eq!(schema.date_format, "%m/%d/%Y");
+    }
+}
@@ -0,0 +1,24 @@
+use chrono::NaiveDate;
+
+use super::{CompanyPriceData, CsvSchema, PriceDataItem};
+
+/// A day that traded at a single price, its open, high, low and adjusted close are all `close`.
+pub fn item(date: NaiveDate, close: f64, volume: i64) -> PriceDataItem {
+    PriceDataItem {
+        date,
+        open: close,
+        high: close,
+        low: close,
+        close,
+        volume,
+        adjusted_close: close,
+    }
+}
+
+/// A company listed on the nyse in the kaggle layout, with the given rows.
+pub fn company(symbol: &str, price_data: Vec<PriceDataItem>) -> CompanyPriceData {
+    let mut company =
+        CompanyPriceData::new(symbol.to_string(), "nyse".to_string(), CsvSchema::kaggle());
+    company.price_data = price_data;
+    company
+}
@@ -0,0 +1,448 @@
+use std::{collections::HashMap, fmt};
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use super::{Company, CompanyPriceData, PriceDataItem};
+
+/// Decides what happens to a row that fails validation.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationPolicy {
+    /// Keep the row as it is and only list it in the quality report.
+    #[default]
+    Flag,
+    /// Fix the row where a sensible fix exists, rows that cannot be fixed are only flagged.
+    Repair,
+    /// Remove the row.
+    Drop,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ValidationConfig {
+    #[serde(default)]
+    pub policy: ValidationPolicy,
+    // The largest close to close move, as a fraction of the previous close, that is considered plausible.
+    #[serde(default = "default_max_daily_move")]
+    pub max_daily_move: f64,
+}
+
+fn default_max_daily_move() -> f64 {
+    0.5
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        ValidationConfig {
+            policy: ValidationPolicy::default(),
+            max_daily_move: default_max_daily_move(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QualityIssue {
+    /// More than one row for the same date.
+    DuplicateDate,
+    /// A row dated before the row above it.
+    OutOfOrder,
+    /// A zero, negative or non-numeric price.
+    NonPositivePrice,
+    /// The low is above the high, or the open or close lies outside of the low to high range.
+    InvalidOhlc,
+    /// The adjusted close moved further than `max_daily_move` from the previous adjusted close.
+    PriceJump,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QualityAction {
+    Flagged,
+    Repaired,
+    Dropped,
+}
+
+#[derive(Debug, Clone)]
+pub struct QualityFinding {
+    pub date: NaiveDate,
+    pub issue: QualityIssue,
+    pub action: QualityAction,
+}
+
+#[derive(Debug, Clone)]
+pub struct QualityReport {
+    pub company: Company,
+    pub findings: Vec<QualityFinding>,
+}
+
+impl QualityReport {
+    pub fn new(company: Company) -> QualityReport {
+        QualityReport {
+            company,
+            findings: Vec::new(),
+        }
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    pub fn count(&self, action: QualityAction) -> usize {
+        self.findings
+            .iter()
+            .filter(|finding| finding.action == action)
+            .count()
+    }
+
+    fn push(&mut self, date: NaiveDate, issue: QualityIssue, action: QualityAction) {
+        self.findings.push(QualityFinding {
+            date,
+            issue,
+            action,
+        });
+    }
+}
+
+impl fmt::Display for QualityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut counts: HashMap<QualityIssue, usize> = HashMap::new();
+        for finding in self.findings.iter() {
+            *counts.entry(finding.issue).or_default() += 1;
+        }
+        let mut counts: Vec<String> = counts
+            .iter()
+            .map(|(issue, count)| format!("{issue:?}: {count}"))
+            .collect();
+        counts.sort();
+
+        let first = self.findings.iter().map(|finding| finding.date).min();
+        let last = self.findings.iter().map(|finding| finding.date).max();
+
+        write!(
+            f,
+            "{exchange}/{symbol}: {issues} between {first} and {last} (flagged {flagged}, repaired {repaired}, dropped {dropped})",
+            exchange = self.company.exchange,
+            symbol = self.company.symbol,
+            issues = counts.join(", "),
+            first = first.unwrap_or_default(),
+            last = last.unwrap_or_default(),
+            flagged = self.count(QualityAction::Flagged),
+            repaired = self.count(QualityAction::Repaired),
+            dropped = self.count(QualityAction::Dropped),
+        )
+    }
+}
+
+impl CompanyPriceData {
+    /// Checks the price series row by row and applies the validation policy to every row that fails a check.
+    pub fn validate(&mut self, config: &ValidationConfig) -> QualityReport {
+        let mut report = QualityReport::new(self.company());
+        let policy = config.policy;
+
+        let rows = std::mem::take(&mut self.price_data);
+        let rows = check_order(rows, policy, &mut report);
+        let rows = check_duplicates(rows, policy, &mut report);
+
+        let mut price_data: Vec<PriceDataItem> = Vec::with_capacity(rows.len());
+        // the row before the current one, whether or not it was kept.
+        let mut raw_prev: Option<PriceDataItem> = None;
+        for mut row in rows {
+            let prev = price_data.last().cloned();
+            let raw_prev = raw_prev.replace(row);
+
+            if !is_positive(row.close)
+                || ![row.open, row.high, row.low].into_iter().all(is_positive)
+            {
+                match (policy, prev) {
+                    (ValidationPolicy::Drop, _) => {
+                        report.push(
+                            row.date,
+                            QualityIssue::NonPositivePrice,
+                            QualityAction::Dropped,
+                        );
+                        continue;
+                    }
+                    (ValidationPolicy::Repair, Some(prev)) if !is_positive(row.close) => {
+                        // without a close there is nothing to trade on, the previous close is carried forward.
+                        row.open = prev.close;
+                        row.high = prev.close;
+                        row.low = prev.close;
+                        row.close = prev.close;
+                        row.adjusted_close = prev.adjusted_close;
+                        row.volume = 0;
+                        report.push(
+                            row.date,
+                            QualityIssue::NonPositivePrice,
+                            QualityAction::Repaired,
+                        );
+                    }
+                    (ValidationPolicy::Repair, _) if is_positive(row.close) => {
+                        for price in [&mut row.open, &mut row.high, &mut row.low] {
+                            if !is_positive(*price) {
+                                *price = row.close;
+                            }
+                        }
+                        report.push(
+                            row.date,
+                            QualityIssue::NonPositivePrice,
+                            QualityAction::Repaired,
+                        );
+                    }
+                    _ => report.push(
+                        row.date,
+                        QualityIssue::NonPositivePrice,
+                        QualityAction::Flagged,
+                    ),
+                }
+            }
+
+            let high = row.open.max(row.high).max(row.low).max(row.close);
+            let low = row.open.min(row.high).min(row.low).min(row.close);
+            if row.high != high || row.low != low {
+                match policy {
+                    ValidationPolicy::Drop => {
+                        report.push(row.date, QualityIssue::InvalidOhlc, QualityAction::Dropped);
+                        continue;
+                    }
+                    ValidationPolicy::Repair => {
+                        row.high = high;
+                        row.low = low;
+                        report.push(row.date, QualityIssue::InvalidOhlc, QualityAction::Repaired);
+                    }
+                    ValidationPolicy::Flag => {
+                        report.push(row.date, QualityIssue::InvalidOhlc, QualityAction::Flagged)
+                    }
+                }
+            }
+
+            // adjusted closes are compared, so that a split or a dividend is not mistaken for a jump. After a
+            // dropped jump the next row is also compared with the dropped row, a level that persists is a real
+            // move and the series continues from it instead of every following row being dropped.
+            let moved = |from: Option<PriceDataItem>| {
+                from.is_some_and(|from| {
+                    is_positive(from.adjusted_close)
+                        && (row.adjusted_close / from.adjusted_close - 1.0).abs()
+                            > config.max_daily_move
+                })
+            };
+            if moved(prev) && moved(raw_prev) {
+                // there is no way to tell a bad print from a real move, so a jump is never repaired.
+                if policy == ValidationPolicy::Drop {
+                    report.push(row.date, QualityIssue::PriceJump, QualityAction::Dropped);
+                    continue;
+                }
+                report.push(row.date, QualityIssue::PriceJump, QualityAction::Flagged);
+            }
+
+            price_data.push(row);
+        }
+
+        self.price_data = price_data;
+        report
+    }
+}
+
+fn is_positive(price: f64) -> bool {
+    price.is_finite() && price > 0.0
+}
+
+fn check_order(
+    rows: Vec<PriceDataItem>,
+    policy: ValidationPolicy,
+    report: &mut QualityReport,
+) -> Vec<PriceDataItem> {
+    let mut latest = NaiveDate::MIN;
+    let mut ordered = Vec::with_capacity(rows.len());
+    let mut out_of_order = false;
+
+    for row in rows {
+        if row.date < latest {
+            out_of_order = true;
+            match policy {
+                ValidationPolicy::Drop => {
+                    report.push(row.date, QualityIssue::OutOfOrder, QualityAction::Dropped);
+                    continue;
+                }
+                ValidationPolicy::Repair => {
+                    report.push(row.date, QualityIssue::OutOfOrder, QualityAction::Repaired)
+                }
+                ValidationPolicy::Flag => {
+                    report.push(row.date, QualityIssue::OutOfOrder, QualityAction::Flagged)
+                }
+            }
+        }
+        latest = latest.max(row.date);
+        ordered.push(row);
+    }
+
+    if out_of_order && policy == ValidationPolicy::Repair {
+        ordered.sort_by_key(|row| row.date);
+    }
+    ordered
+}
+
+// Expects the rows to be sorted, duplicates that are not next to each other are caught by `check_order`.
+fn check_duplicates(
+    rows: Vec<PriceDataItem>,
+    policy: ValidationPolicy,
+    report: &mut QualityReport,
+) -> Vec<PriceDataItem> {
+    let mut deduped: Vec<PriceDataItem> = Vec::with_capacity(rows.len());
+    // under the drop policy every row of a duplicated date goes, as there is no telling which one is right.
+    let mut dropped_date = None;
+
+    for row in rows {
+        let duplicate = deduped.last().map(|prev| prev.date) == Some(row.date)
+            || dropped_date == Some(row.date);
+        if !duplicate {
+            deduped.push(row);
+            continue;
+        }
+
+        match policy {
+            ValidationPolicy::Drop => {
+                if deduped.last().map(|prev| prev.date) == Some(row.date) {
+                    deduped.pop();
+                    report.push(
+                        row.date,
+                        QualityIssue::DuplicateDate,
+                        QualityAction::Dropped,
+                    );
+                }
+                dropped_date = Some(row.date);
+                report.push(
+                    row.date,
+                    QualityIssue::DuplicateDate,
+                    QualityAction::Dropped,
+                );
+            }
+            // the later row is kept, vendors append corrections after the original print.
+            ValidationPolicy::Repair => {
+                *deduped.last_mut().unwrap() = row;
+                report.push(
+                    row.date,
+                    QualityIssue::DuplicateDate,
+                    QualityAction::Repaired,
+                );
+            }
+            ValidationPolicy::Flag => {
+                deduped.push(row);
+                report.push(
+                    row.date,
+                    QualityIssue::DuplicateDate,
+                    QualityAction::Flagged,
+                );
+            }
+        }
+    }
+
+    deduped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price_data::testing;
+
+    fn item(day: u32, close: f64) -> PriceDataItem {
+        testing::item(NaiveDate::from_ymd_opt(2001, 1, day).unwrap(), close, 100)
+    }
+
+    fn validate(
+        price_data: Vec<PriceDataItem>,
+        policy: ValidationPolicy,
+    ) -> (Vec<PriceDataItem>, QualityReport) {
+        let mut company = testing::company("TEST", price_data);
+        let report = company.validate(&ValidationConfig {
+            policy,
+            ..ValidationConfig::default()
+        });
+        (company.price_data, report)
+    }
+
+    fn closes(price_data: &[PriceDataItem]) -> Vec<f64> {
+        price_data.iter().map(|item| item.close).collect()
+    }
+
+    #[test]
+    fn drop_removes_a_single_spike() {
+        let rows = vec![item(1, 10.0), item(2, 30.0), item(3, 10.5), item(4, 11.0)];
+        let (price_data, report) = validate(rows, ValidationPolicy::Drop);
+
+        assert_eq!(closes(&price_data), vec![10.0, 10.5, 11.0]);
+        assert_eq!(report.count(QualityAction::Dropped), 1);
+    }
+
+    #[test]
+    fn drop_keeps_the_series_after_an_unadjusted_split() {
+        let rows = vec![item(1, 100.0), item(2, 25.0), item(3, 26.0), item(4, 27.0)];
+        let (price_data, report) = validate(rows, ValidationPolicy::Drop);
+
+        assert_eq!(closes(&price_data), vec![100.0, 26.0, 27.0]);
+        assert_eq!(report.count(QualityAction::Dropped), 1);
+    }
+
+    #[test]
+    fn adjusted_split_is_not_a_jump() {
+        let rows = vec![
+            PriceDataItem {
+                adjusted_close: 25.0,
+                ..item(1, 100.0)
+            },
+            item(2, 25.5),
+            item(3, 26.0),
+        ];
+        let (price_data, report) = validate(rows, ValidationPolicy::Drop);
+
+        assert_eq!(price_data.len(), 3);
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn flag_keeps_every_row() {
+        let rows = vec![item(1, 10.0), item(2, 30.0), item(3, 10.0), item(2, -1.0)];
+        let (price_data, report) = validate(rows, ValidationPolicy::Flag);
+
+        assert_eq!(price_data.len(), 4);
+        assert_eq!(report.count(QualityAction::Flagged), report.findings.len());
+        let issues: Vec<QualityIssue> = report.findings.iter().map(|f| f.issue).collect();
+        assert!(issues.contains(&QualityIssue::PriceJump));
+        assert!(issues.contains(&QualityIssue::OutOfOrder));
+        assert!(issues.contains(&QualityIssue::NonPositivePrice));
+    }
+
+    #[test]
+    fn repair_sorts_and_keeps_the_later_duplicate() {
+        let rows = vec![item(1, 10.0), item(3, 11.0), item(2, 10.5), item(3, 11.5)];
+        let (price_data, report) = validate(rows, ValidationPolicy::Repair);
+
+        assert_eq!(closes(&price_data), vec![10.0, 10.5, 11.5]);
+        assert_eq!(report.count(QualityAction::Repaired), 2);
+    }
+
+    #[test]
+    fn drop_removes_every_row_of_a_duplicated_date() {
+        let rows = vec![item(1, 10.0), item(2, 10.5), item(2, 10.6), item(3, 11.0)];
+        let (price_data, _) = validate(rows, ValidationPolicy::Drop);
+
+        assert_eq!(closes(&price_data), vec![10.0, 11.0]);
+    }
+
+    #[test]
+    fn repair_carries_the_previous_close_and_fixes_the_range() {
+        let rows = vec![
+            item(1, 10.0),
+            item(2, 0.0),
+            PriceDataItem {
+                high: 10.0,
+                low: 12.0,
+                ..item(3, 11.0)
+            },
+        ];
+        let (price_data, report) = validate(rows, ValidationPolicy::Repair);
+
+        assert_eq!(closes(&price_data), vec![10.0, 10.0, 11.0]);
+        assert_eq!(price_data[1].volume, 0);
+        assert_eq!((price_data[2].low, price_data[2].high), (10.0, 12.0));
+        assert_eq!(report.count(QualityAction::Repaired), 2);
+    }
+}